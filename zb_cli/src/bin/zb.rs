@@ -1,7 +1,7 @@
 use clap::Parser;
 use console::style;
 use zb_cli::{
-    cli::{Cli, Commands},
+    cli::{BundleCommands, CacheCommands, Cli, Commands},
     commands,
     init::ensure_init,
     utils::get_root_path,
@@ -35,28 +35,99 @@ async fn run(cli: Cli) -> Result<(), zb_core::Error> {
     }
 
     let mut installer = create_installer(&root, &prefix, cli.concurrency)?;
+    installer.set_formula_index(cli.formula_index);
+    installer.set_offline(cli.offline);
+    installer.set_remote_cache(cli.remote_cache);
 
-    match cli.command {
+    let result = match cli.command {
         Commands::Init { .. } => unreachable!(),
         Commands::Completion { .. } => unreachable!(),
-        Commands::Install { formulas, no_link } => {
-            commands::install::execute(&mut installer, formulas, no_link).await
-        }
-        Commands::Bundle { file, no_link } => {
-            commands::bundle::execute(&mut installer, &file, no_link).await
-        }
-        Commands::Uninstall { formulas, all } => {
-            commands::uninstall::execute(&mut installer, formulas, all)
+        Commands::Install {
+            formulas,
+            formula_json,
+            no_link,
+        } => commands::install::execute(&mut installer, formulas, formula_json, no_link).await,
+        Commands::Bottle {
+            formula,
+            output_dir,
+            root_url,
+            tag,
+        } => commands::bottle::execute(&mut installer, formula, &output_dir, root_url, tag),
+        Commands::Bundle {
+            file,
+            no_link,
+            locked,
+            command,
+        } => match command {
+            None => commands::bundle::execute(&mut installer, &file, no_link, locked).await,
+            Some(BundleCommands::Dump { force }) => {
+                commands::bundle::dump(&mut installer, &file, force)
+            }
+            Some(BundleCommands::Check) => commands::bundle::check(&mut installer, &file),
+            Some(BundleCommands::Cleanup { force }) => {
+                commands::bundle::cleanup(&mut installer, &file, force)
+            }
+        },
+        Commands::Cache { command } => match command {
+            CacheCommands::Export {
+                file,
+                formulas,
+                target,
+            } => commands::cache::export(&mut installer, &file, formulas, target).await,
+            CacheCommands::Import { file } => commands::cache::import(&mut installer, &file),
+        },
+        Commands::Fetch {
+            formulas,
+            target,
+            bottle_tag,
+            unpack,
+        } => commands::fetch::execute(&mut installer, formulas, target, bottle_tag, unpack).await,
+        Commands::Lock { file } => commands::lock::execute(&mut installer, &file).await,
+        Commands::Uninstall {
+            formulas,
+            all,
+            ignore_dependencies,
+        } => commands::uninstall::execute(&mut installer, formulas, all, ignore_dependencies),
+        Commands::Upgrade { formulas } => {
+            commands::upgrade::execute(&mut installer, formulas).await
         }
         Commands::Migrate { yes, force } => {
             commands::migrate::execute(&mut installer, yes, force).await
         }
-        Commands::List => commands::list::execute(&mut installer),
+        Commands::Autoremove { dry_run } => commands::autoremove::execute(&mut installer, dry_run),
+        Commands::List { leaves } => commands::list::execute(&mut installer, leaves),
+        Commands::Outdated { formulas, json } => {
+            commands::outdated::execute(&mut installer, formulas, json).await
+        }
         Commands::Info { formula } => commands::info::execute(&mut installer, formula),
+        Commands::Deps {
+            formulas,
+            tree,
+            topological,
+            installed,
+            dot,
+        } => {
+            commands::deps::execute(&mut installer, formulas, tree, topological, installed, dot)
+                .await
+        }
+        Commands::Search { query, desc } => {
+            commands::search::execute(&mut installer, query, desc).await
+        }
+        Commands::Serve { listen, formulas } => {
+            commands::serve::execute(&mut installer, &root, &listen, formulas).await
+        }
+        Commands::Uses { formula } => commands::uses::execute(&mut installer, formula),
+        Commands::Why { formula } => commands::why::execute(&mut installer, formula),
+        Commands::Tap { name, source } => commands::tap::execute(&mut installer, name, source),
+        Commands::Untap { name } => commands::tap::untap(&mut installer, name),
         Commands::Gc => commands::gc::execute(&mut installer),
         Commands::Reset { yes } => commands::reset::execute(&root, &prefix, yes),
         Commands::Run { formula, args } => {
             commands::run::execute(&mut installer, formula, args).await
         }
-    }
+    };
+
+    // Returning shuts the runtime down, which would cancel uploads still in flight
+    installer.finish_uploads().await;
+    result
 }
@@ -0,0 +1,651 @@
+//! Parser for Homebrew Bundle's Ruby-flavoured `Brewfile` syntax.
+//!
+//! Only the subset that shows up in real Brewfiles is understood: one directive per
+//! line (`brew "jq"`, `tap "user/repo"`, `cask "firefox"`, ...) with optional
+//! `key: value` options, continued onto following lines after a trailing `,` or an
+//! open bracket. Bare formula names, one per line, are still accepted.
+//! Ruby blocks such as `if OS.mac?` ... `end` can't be evaluated, so they are
+//! skipped as a whole and reported.
+
+/// A formula entry zerobrew will install
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrewfileFormula {
+    pub name: String,
+    /// `link: false` installs the keg without linking it into the prefix
+    pub link: bool,
+}
+
+/// An entry or option zerobrew doesn't act on, kept so it can be reported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedEntry {
+    pub line: usize,
+    pub entry: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Brewfile {
+    pub formulas: Vec<BrewfileFormula>,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+impl Brewfile {
+    pub fn names(&self) -> Vec<String> {
+        self.formulas.iter().map(|f| f.name.clone()).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(String),
+    Ident(String),
+    /// `key:` in Ruby 1.9 hash syntax
+    Key(String),
+    /// `:symbol`, including the key of a `:key => value` pair
+    Symbol(String),
+    Comma,
+    Arrow,
+    Open(char),
+    Close(char),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bool(bool),
+    Other(String),
+}
+
+/// Directives Homebrew Bundle understands that zerobrew has nothing to install for
+const UNSUPPORTED_DIRECTIVES: &[(&str, &str)] = &[
+    ("tap", "register taps with `zb tap` instead"),
+    ("cask", "casks are not supported"),
+    ("mas", "Mac App Store apps are not supported"),
+    ("vscode", "VS Code extensions are not supported"),
+    ("whalebrew", "whalebrew images are not supported"),
+    ("cask_args", "casks are not supported"),
+];
+
+/// Keywords that open a block closed by `end` when they start a line
+const BLOCK_KEYWORDS: &[&str] = &[
+    "if", "unless", "case", "while", "until", "begin", "def", "class", "module",
+];
+
+/// Keywords that only make sense inside an open block
+const CONTINUATION_KEYWORDS: &[&str] = &[
+    "else", "elsif", "when", "in", "then", "rescue", "ensure", "end",
+];
+
+/// A Ruby block being skipped, from its opening line to its matching `end`
+struct OpenBlock {
+    line: usize,
+    entry: String,
+    depth: usize,
+}
+
+pub fn parse_brewfile(contents: &str) -> Result<Brewfile, zb_core::Error> {
+    let mut brewfile = Brewfile::default();
+    let mut block: Option<OpenBlock> = None;
+
+    for (line_number, line) in logical_lines(contents) {
+        let line = line.as_str();
+        let (opens, closes) = block_keywords(line);
+
+        if let Some(open) = block.as_mut() {
+            // Anything inside a block is Ruby we don't evaluate; only track nesting
+            open.depth = (open.depth + opens).saturating_sub(closes);
+            if open.depth == 0 {
+                let open = block.take().expect("block is open");
+                brewfile.skipped.push(SkippedEntry {
+                    line: open.line,
+                    entry: open.entry,
+                    reason: format!(
+                        "Ruby blocks are not supported; lines {}-{line_number} were ignored",
+                        open.line
+                    ),
+                });
+            }
+            continue;
+        }
+
+        if closes > opens {
+            return Err(syntax_error(line_number, "`end` without a matching block"));
+        }
+        if opens > closes {
+            block = Some(OpenBlock {
+                line: line_number,
+                entry: line.trim().to_string(),
+                depth: opens - closes,
+            });
+            continue;
+        }
+        if let Some(word) = ruby_words(line).first()
+            && CONTINUATION_KEYWORDS.contains(&word.as_str())
+        {
+            return Err(syntax_error(
+                line_number,
+                &format!("`{word}` outside of a block"),
+            ));
+        }
+
+        let tokens = tokenize(line).map_err(|message| syntax_error(line_number, &message))?;
+
+        let Some(first) = tokens.first() else {
+            continue;
+        };
+
+        // Plain manifests list one bare formula name per line
+        if tokens.len() == 1
+            && let Token::Ident(name) = first
+            && !is_directive(name)
+        {
+            brewfile.formulas.push(BrewfileFormula {
+                name: name.clone(),
+                link: true,
+            });
+            continue;
+        }
+
+        let Token::Ident(directive) = first else {
+            return Err(syntax_error(
+                line_number,
+                "expected a directive such as `brew`",
+            ));
+        };
+
+        if let Some((_, reason)) = UNSUPPORTED_DIRECTIVES
+            .iter()
+            .find(|(name, _)| name == directive)
+        {
+            brewfile.skipped.push(SkippedEntry {
+                line: line_number,
+                entry: line.trim().to_string(),
+                reason: reason.to_string(),
+            });
+            continue;
+        }
+
+        // Ruby conditions can't be evaluated here, so never guess which way they go.
+        // This also covers one-line blocks such as `if OS.mac? then brew "x" end`.
+        if opens > 0
+            || tokens
+                .iter()
+                .any(|t| matches!(t, Token::Ident(word) if word == "if" || word == "unless"))
+        {
+            brewfile.skipped.push(SkippedEntry {
+                line: line_number,
+                entry: line.trim().to_string(),
+                reason: "conditional entries are not supported".to_string(),
+            });
+            continue;
+        }
+
+        if directive != "brew" {
+            brewfile.skipped.push(SkippedEntry {
+                line: line_number,
+                entry: line.trim().to_string(),
+                reason: format!("`{directive}` entries are not supported"),
+            });
+            continue;
+        }
+
+        let (name, options) =
+            parse_arguments(&tokens[1..]).map_err(|message| syntax_error(line_number, &message))?;
+
+        let mut formula = BrewfileFormula { name, link: true };
+        for (key, value) in options {
+            match (key.as_str(), value) {
+                ("link", Value::Bool(link)) => formula.link = link,
+                ("link", Value::Other(value)) if value == "overwrite" => {
+                    formula.link = true;
+                    brewfile.skipped.push(SkippedEntry {
+                        line: line_number,
+                        entry: format!("{} link: :overwrite", formula.name),
+                        reason: "linked without overwriting existing files".to_string(),
+                    });
+                }
+                ("link", _) => {
+                    return Err(syntax_error(
+                        line_number,
+                        "`link:` must be true, false or :overwrite",
+                    ));
+                }
+                (key, _) => brewfile.skipped.push(SkippedEntry {
+                    line: line_number,
+                    entry: format!("{} {key}:", formula.name),
+                    reason: format!("the `{key}` option is not supported and was ignored"),
+                }),
+            }
+        }
+        brewfile.formulas.push(formula);
+    }
+
+    if let Some(open) = block {
+        return Err(syntax_error(open.line, "block is never closed with `end`"));
+    }
+
+    Ok(brewfile)
+}
+
+/// Join each line ending in `,` or inside an open bracket with the lines after it,
+/// numbering the result by the line it starts on
+fn logical_lines(contents: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+
+    for (index, line) in contents.lines().enumerate() {
+        let (line_number, text) = match pending.take() {
+            Some((start, text)) => (start, format!("{text} {}", line.trim())),
+            None => (index + 1, line.to_string()),
+        };
+
+        let (code, open_brackets) = split_comment(&text);
+        let code = code.trim_end();
+        if code.ends_with(',') || open_brackets > 0 {
+            // A comment would swallow everything joined after it
+            pending = Some((line_number, code.to_string()));
+        } else {
+            lines.push((line_number, text));
+        }
+    }
+
+    lines.extend(pending);
+    lines
+}
+
+/// The code before a line's comment, and how many brackets it leaves open
+fn split_comment(line: &str) -> (&str, usize) {
+    let mut open_brackets = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '#' => return (&line[..i], open_brackets),
+            '(' | '[' | '{' => open_brackets += 1,
+            ')' | ']' | '}' => open_brackets = open_brackets.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    (line, open_brackets)
+}
+
+fn is_directive(word: &str) -> bool {
+    word == "brew"
+        || UNSUPPORTED_DIRECTIVES.iter().any(|(name, _)| *name == word)
+        || BLOCK_KEYWORDS.contains(&word)
+        || CONTINUATION_KEYWORDS.contains(&word)
+        || word == "do"
+}
+
+/// Count the blocks a line opens and the `end`s that close them
+fn block_keywords(line: &str) -> (usize, usize) {
+    let words = ruby_words(line);
+    let leading = words
+        .first()
+        .is_some_and(|word| BLOCK_KEYWORDS.contains(&word.as_str()));
+    let opens = usize::from(leading) + words.iter().filter(|w| *w == "do").count();
+    let closes = words.iter().filter(|w| *w == "end").count();
+    (opens, closes)
+}
+
+/// Bare words of a line outside strings, symbols and comments.
+///
+/// Unlike `tokenize` this never fails, since lines inside skipped blocks can hold
+/// arbitrary Ruby.
+fn ruby_words(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut words = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '#' => break,
+            '"' | '\'' => {
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            c if is_word_char(c) => {
+                let start = i;
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                let symbol = start > 0 && chars[start - 1] == ':';
+                let key = chars.get(i) == Some(&':') && chars.get(i + 1) != Some(&':');
+                if !symbol && !key {
+                    words.push(chars[start..i].iter().collect());
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    words
+}
+
+fn syntax_error(line: usize, message: &str) -> zb_core::Error {
+    zb_core::Error::FileError {
+        message: format!("Brewfile line {line}: {message}"),
+    }
+}
+
+/// Parse `"name", key: value, ...`, with or without surrounding parentheses
+fn parse_arguments(tokens: &[Token]) -> Result<(String, Vec<(String, Value)>), String> {
+    let tokens = match (tokens.first(), tokens.last()) {
+        (Some(Token::Open('(')), Some(Token::Close(')'))) => &tokens[1..tokens.len() - 1],
+        _ => tokens,
+    };
+
+    let Some(Token::Str(name)) = tokens.first() else {
+        return Err("expected a quoted formula name after `brew`".to_string());
+    };
+
+    let mut options = Vec::new();
+    let mut rest = &tokens[1..];
+    while !rest.is_empty() {
+        let Some((Token::Comma, after_comma)) = rest.split_first() else {
+            return Err("expected `,` between arguments".to_string());
+        };
+
+        let (key, after_key) = match after_comma {
+            [Token::Key(key), tail @ ..] => (key.clone(), tail),
+            [Token::Symbol(key), Token::Arrow, tail @ ..] => (key.clone(), tail),
+            [Token::Str(key), Token::Arrow, tail @ ..] => (key.clone(), tail),
+            _ => return Err("expected an option such as `link: false`".to_string()),
+        };
+
+        let (value, after_value) = parse_value(after_key)?;
+        options.push((key, value));
+        rest = after_value;
+    }
+
+    Ok((name.clone(), options))
+}
+
+fn parse_value(tokens: &[Token]) -> Result<(Value, &[Token]), String> {
+    match tokens {
+        [Token::Ident(word), tail @ ..] if word == "true" => Ok((Value::Bool(true), tail)),
+        [Token::Ident(word), tail @ ..] if word == "false" => Ok((Value::Bool(false), tail)),
+        [
+            Token::Str(value) | Token::Symbol(value) | Token::Ident(value),
+            tail @ ..,
+        ] => Ok((Value::Other(value.clone()), tail)),
+        [Token::Open(open), ..] => {
+            // Arrays and hashes are only ever skipped, so just find the matching close
+            let mut depth = 0;
+            for (i, token) in tokens.iter().enumerate() {
+                match token {
+                    Token::Open(_) => depth += 1,
+                    Token::Close(_) => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok((Value::Other(open.to_string()), &tokens[i + 1..]));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Err("unbalanced brackets".to_string())
+        }
+        _ => Err("expected an option value".to_string()),
+    }
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '#' => break,
+            c if c.is_whitespace() => i += 1,
+            '"' | '\'' => {
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("unterminated string".to_string()),
+                        Some('\\') if chars.get(i + 1).is_some() => {
+                            value.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&ch) if ch == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some(&ch) => {
+                            value.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '=' if chars.get(i + 1) == Some(&'>') => {
+                tokens.push(Token::Arrow);
+                i += 2;
+            }
+            '(' | '[' | '{' => {
+                tokens.push(Token::Open(c));
+                i += 1;
+            }
+            ')' | ']' | '}' => {
+                tokens.push(Token::Close(c));
+                i += 1;
+            }
+            ':' if chars.get(i + 1).is_some_and(|n| is_word_char(*n)) => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                tokens.push(Token::Symbol(chars[start..i].iter().collect()));
+            }
+            c if is_word_char(c) => {
+                let start = i;
+                while i < chars.len() && is_word_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if chars.get(i) == Some(&':') && chars.get(i + 1) != Some(&':') {
+                    i += 1;
+                    tokens.push(Token::Key(word));
+                } else {
+                    tokens.push(Token::Ident(word));
+                }
+            }
+            other => return Err(format!("unexpected character '{other}'")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Characters allowed in bare words; formula names use `@`, `+`, `-`, `.` and `/`
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '@' | '+' | '.' | '/' | '?' | '!')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula(name: &str, link: bool) -> BrewfileFormula {
+        BrewfileFormula {
+            name: name.to_string(),
+            link,
+        }
+    }
+
+    #[test]
+    fn parses_homebrew_bundle_syntax() {
+        let brewfile = parse_brewfile(
+            r#"
+            # Development tools
+            tap "homebrew/bundle"
+            brew "jq"
+            brew 'openssl@3'
+            brew("git")
+            brew "postgresql@16", restart_service: :changed
+            brew "gettext", link: false # keg-only
+            brew "python@3.12", :link => false
+            cask "firefox"
+            mas "Xcode", id: 497799835
+            vscode "rust-lang.rust-analyzer"
+            brew "gnu-sed" if OS.mac?
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            brewfile.formulas,
+            vec![
+                formula("jq", true),
+                formula("openssl@3", true),
+                formula("git", true),
+                formula("postgresql@16", true),
+                formula("gettext", false),
+                formula("python@3.12", false),
+            ]
+        );
+
+        let skipped: Vec<usize> = brewfile.skipped.iter().map(|s| s.line).collect();
+        assert_eq!(skipped, vec![3, 7, 10, 11, 12, 13]);
+        assert!(brewfile.skipped[1].reason.contains("restart_service"));
+    }
+
+    #[test]
+    fn keeps_plain_manifests_working() {
+        let brewfile = parse_brewfile("jq\nwget # comment\nhomebrew/core/git\n").unwrap();
+        assert_eq!(brewfile.names(), vec!["jq", "wget", "homebrew/core/git"]);
+        assert!(brewfile.skipped.is_empty());
+    }
+
+    #[test]
+    fn skips_array_and_hash_options() {
+        let brewfile =
+            parse_brewfile(r#"brew "vim", args: ["with-lua"], conflicts_with: ["macvim"]"#)
+                .unwrap();
+        assert_eq!(brewfile.names(), vec!["vim"]);
+        assert_eq!(brewfile.skipped.len(), 2);
+    }
+
+    #[test]
+    fn joins_entries_continued_across_lines() {
+        let brewfile = parse_brewfile(
+            r#"
+            brew "gettext",
+              link: false # keg-only
+            brew "vim", args: [
+              "with-lua", # scripting
+              "with-python",
+            ], link: false
+            brew("git",
+                 restart_service: :changed)
+            brew "jq"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            brewfile.formulas,
+            vec![
+                formula("gettext", false),
+                formula("vim", false),
+                formula("git", true),
+                formula("jq", true),
+            ]
+        );
+        let skipped: Vec<usize> = brewfile.skipped.iter().map(|s| s.line).collect();
+        assert_eq!(skipped, vec![4, 8]);
+
+        let err = parse_brewfile("brew \"jq\"\nbrew \"wget\",\n  link: \"no\"\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+        assert!(parse_brewfile("brew \"vim\", args: [\"with-lua\",\n").is_err());
+    }
+
+    #[test]
+    fn reports_line_numbers_for_syntax_errors() {
+        let err = parse_brewfile("brew \"jq\"\nbrew jq, link: false\n").unwrap_err();
+        match err {
+            zb_core::Error::FileError { message } => {
+                assert!(message.contains("line 2"), "{message}")
+            }
+            other => panic!("expected file error, got {other:?}"),
+        }
+
+        assert!(parse_brewfile("brew \"jq").is_err());
+        assert!(parse_brewfile("brew \"jq\", link: \"no\"").is_err());
+    }
+
+    #[test]
+    fn skips_whole_ruby_blocks() {
+        let brewfile = parse_brewfile(
+            r#"
+            brew "jq"
+            if OS.mac?
+              brew "gnu-sed"
+              if Hardware::CPU.arm?
+                brew "mas"
+              end
+            else
+              brew "gcc"
+            end
+            unless ENV["CI"]
+              brew "htop"
+            end
+            %w[wget curl].each do |name|
+              brew name
+            end
+            brew "git"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(brewfile.names(), vec!["jq", "git"]);
+        let skipped: Vec<usize> = brewfile.skipped.iter().map(|s| s.line).collect();
+        assert_eq!(skipped, vec![3, 11, 14]);
+        assert!(brewfile.skipped[0].reason.contains("lines 3-10"));
+    }
+
+    #[test]
+    fn never_treats_ruby_keywords_as_formulas() {
+        assert!(parse_brewfile("jq\nend\n").is_err());
+        assert!(parse_brewfile("else\n").is_err());
+        assert!(parse_brewfile("if OS.mac?\n  brew \"jq\"\n").is_err());
+
+        let brewfile = parse_brewfile("if OS.linux? then brew \"gcc\" end\n").unwrap();
+        assert!(brewfile.formulas.is_empty());
+        assert_eq!(brewfile.skipped.len(), 1);
+    }
+
+    #[test]
+    fn accepts_link_overwrite_as_linked() {
+        let brewfile = parse_brewfile(r#"brew "python@3.12", link: :overwrite"#).unwrap();
+        assert_eq!(brewfile.formulas, vec![formula("python@3.12", true)]);
+        assert_eq!(brewfile.skipped.len(), 1);
+        assert!(brewfile.skipped[0].entry.contains("overwrite"));
+    }
+}
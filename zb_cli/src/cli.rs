@@ -23,6 +23,15 @@ pub struct Cli {
     )]
     pub auto_init: bool,
 
+    #[arg(long, global = true, env = "ZEROBREW_FORMULA_INDEX")]
+    pub formula_index: bool,
+
+    #[arg(long, global = true, env = "ZEROBREW_OFFLINE")]
+    pub offline: bool,
+
+    #[arg(long, global = true, value_name = "URL", env = "ZEROBREW_REMOTE_CACHE")]
+    pub remote_cache: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -30,22 +39,66 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     Install {
-        #[arg(required = true, num_args = 1..)]
+        #[arg(required_unless_present = "formula_json", num_args = 1..)]
         formulas: Vec<String>,
+        #[arg(long, value_name = "FILE")]
+        formula_json: Vec<PathBuf>,
         #[arg(long)]
         no_link: bool,
     },
+    Bottle {
+        formula: String,
+        #[arg(long, short = 'o', value_name = "DIR", default_value = ".")]
+        output_dir: PathBuf,
+        #[arg(long, value_name = "URL")]
+        root_url: Option<String>,
+        #[arg(long, value_name = "TAG")]
+        tag: Option<String>,
+    },
     Bundle {
-        #[arg(long, short = 'f', value_name = "FILE", default_value = "Brewfile")]
+        #[arg(
+            long,
+            short = 'f',
+            value_name = "FILE",
+            default_value = "Brewfile",
+            global = true
+        )]
         file: PathBuf,
         #[arg(long)]
         no_link: bool,
+        #[arg(long)]
+        locked: bool,
+        #[command(subcommand)]
+        command: Option<BundleCommands>,
+    },
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    Fetch {
+        #[arg(required = true, num_args = 1..)]
+        formulas: Vec<String>,
+        #[arg(long, short = 't', conflicts_with = "bottle_tag")]
+        target: Option<String>,
+        #[arg(long)]
+        bottle_tag: Option<String>,
+        #[arg(long)]
+        unpack: bool,
+    },
+    Lock {
+        #[arg(long, short = 'f', value_name = "FILE", default_value = "Brewfile")]
+        file: PathBuf,
     },
     Uninstall {
         #[arg(required_unless_present = "all", num_args = 1..)]
         formulas: Vec<String>,
         #[arg(long)]
         all: bool,
+        #[arg(long)]
+        ignore_dependencies: bool,
+    },
+    Upgrade {
+        formulas: Vec<String>,
     },
     Migrate {
         #[arg(long, short = 'y')]
@@ -53,10 +106,63 @@ pub enum Commands {
         #[arg(long)]
         force: bool,
     },
-    List,
+    Autoremove {
+        #[arg(long)]
+        dry_run: bool,
+    },
+    List {
+        #[arg(long)]
+        leaves: bool,
+    },
+    Outdated {
+        formulas: Vec<String>,
+        #[arg(long)]
+        json: bool,
+    },
     Info {
         formula: String,
     },
+    Deps {
+        #[arg(required_unless_present = "installed")]
+        formulas: Vec<String>,
+        #[arg(long, conflicts_with_all = ["topological", "dot"])]
+        tree: bool,
+        #[arg(long, conflicts_with = "dot")]
+        topological: bool,
+        #[arg(long)]
+        installed: bool,
+        #[arg(long)]
+        dot: bool,
+    },
+    Search {
+        query: String,
+        #[arg(long)]
+        desc: bool,
+    },
+    Serve {
+        #[arg(
+            long,
+            short = 'l',
+            value_name = "ADDR",
+            default_value = "127.0.0.1:8080"
+        )]
+        listen: String,
+        #[arg(long)]
+        formulas: bool,
+    },
+    Uses {
+        formula: String,
+    },
+    Why {
+        formula: String,
+    },
+    Tap {
+        name: Option<String>,
+        source: Option<String>,
+    },
+    Untap {
+        name: String,
+    },
     Gc,
     Reset {
         #[arg(long, short = 'y')]
@@ -77,3 +183,29 @@ pub enum Commands {
         args: Vec<String>,
     },
 }
+
+#[derive(Subcommand)]
+pub enum BundleCommands {
+    Dump {
+        #[arg(long)]
+        force: bool,
+    },
+    Check,
+    Cleanup {
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    Export {
+        file: PathBuf,
+        formulas: Vec<String>,
+        #[arg(long, short = 't')]
+        target: Option<String>,
+    },
+    Import {
+        file: PathBuf,
+    },
+}
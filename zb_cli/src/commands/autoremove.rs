@@ -0,0 +1,39 @@
+use console::style;
+
+pub fn execute(installer: &mut zb_io::Installer, dry_run: bool) -> Result<(), zb_core::Error> {
+    println!(
+        "{} Looking for unneeded dependencies...",
+        style("==>").cyan().bold()
+    );
+    let orphans = installer.orphaned()?;
+
+    if orphans.is_empty() {
+        println!("No unneeded dependencies to remove.");
+        return Ok(());
+    }
+
+    for keg in &orphans {
+        if dry_run {
+            println!(
+                "    {} {} {}",
+                style("○").dim(),
+                keg.name,
+                style(&keg.version).dim()
+            );
+        } else {
+            print!("    {} {}...", style("○").dim(), keg.name);
+            // Everything that depends on an orphan is itself an orphan
+            installer.uninstall(&keg.name, true)?;
+            println!(" {}", style("✓").green());
+        }
+    }
+
+    println!(
+        "{} {} {} formulas",
+        style("==>").cyan().bold(),
+        if dry_run { "Would remove" } else { "Removed" },
+        style(orphans.len()).green().bold()
+    );
+
+    Ok(())
+}
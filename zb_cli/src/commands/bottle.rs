@@ -0,0 +1,35 @@
+use console::style;
+use std::path::Path;
+
+use crate::utils::resolve_installed_name;
+
+/// Re-package an installed keg as a relocatable bottle plus formula JSON
+pub fn execute(
+    installer: &mut zb_io::Installer,
+    formula: String,
+    output_dir: &Path,
+    root_url: Option<String>,
+    tag: Option<String>,
+) -> Result<(), zb_core::Error> {
+    let name = resolve_installed_name(installer, &formula);
+    println!(
+        "{} Bottling {}...",
+        style("==>").cyan().bold(),
+        style(&name).bold()
+    );
+
+    let built = installer.bottle(&name, output_dir, root_url.as_deref(), tag.as_deref())?;
+
+    println!("    {} {}", style("✓").green(), built.path.display());
+    println!("    {} {}", style("✓").green(), built.json_path.display());
+    println!(
+        "{} Bottled {} {} for {} (sha256 {})",
+        style("==>").cyan().bold(),
+        style(&built.formula.name).bold(),
+        built.formula.versions.stable,
+        built.tag,
+        built.sha256
+    );
+
+    Ok(())
+}
@@ -1,63 +1,271 @@
 use console::style;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::path::Path;
 use std::time::Instant;
 
-use super::install;
+use super::uninstall::dependents_first;
+use super::{install, lock};
+use crate::brewfile::{Brewfile, parse_brewfile};
+use crate::utils::normalize_formula_name;
 
 pub async fn execute(
     installer: &mut zb_io::Installer,
     manifest_path: &Path,
     no_link: bool,
+    locked: bool,
 ) -> Result<(), zb_core::Error> {
-    let formulas = load_manifest(manifest_path)?;
+    if locked {
+        return execute_locked(installer, manifest_path, no_link).await;
+    }
+
+    let start = Instant::now();
+    let brewfile = load_manifest(manifest_path)?;
     println!(
         "{} Installing {} formulas from {}...",
         style("==>").cyan().bold(),
-        style(formulas.len()).green().bold(),
+        style(brewfile.formulas.len()).green().bold(),
         manifest_path.display()
     );
 
+    // Resolve every entry together so shared dependencies are fetched once and
+    // all downloads overlap in a single pipeline
+    let mut plan = installer.plan(&brewfile.names()).await?;
+    plan.skip_link = unlinked(&brewfile, &plan);
+    install::print_plan(&plan);
+
+    let result = install::execute_plan(installer, plan, !no_link).await?;
+
+    println!();
+    println!(
+        "{} Installed {} packages from manifest in {:.2}s",
+        style("==>").cyan().bold(),
+        style(result.installed).green().bold(),
+        start.elapsed().as_secs_f64()
+    );
+    Ok(())
+}
+
+/// Entries marked `link: false`, under the formula names the plan resolved them to
+fn unlinked(brewfile: &Brewfile, plan: &zb_io::InstallPlan) -> BTreeSet<String> {
+    brewfile
+        .formulas
+        .iter()
+        .filter(|f| !f.link)
+        .map(|f| plan.canonical_name(&f.name).to_string())
+        .collect()
+}
+
+/// Install exactly the bottles pinned in the manifest's lockfile, skipping API resolution
+async fn execute_locked(
+    installer: &mut zb_io::Installer,
+    manifest_path: &Path,
+    no_link: bool,
+) -> Result<(), zb_core::Error> {
     let start = Instant::now();
-    for formula in formulas {
-        install::execute(installer, vec![formula], no_link).await?;
+    let brewfile = load_manifest(manifest_path)?;
+    let roots = brewfile.names();
+    let lockfile_path = lock::lockfile_path(manifest_path);
+    let lockfile = lock::read_lockfile(&lockfile_path)?;
+
+    if !lockfile.matches_roots(&roots) {
+        return Err(zb_core::Error::FileError {
+            message: format!(
+                "lockfile {} is out of date with {} (run `zb lock` to update it)",
+                lockfile_path.display(),
+                manifest_path.display()
+            ),
+        });
     }
 
     println!(
-        "{} Finished installing manifest in {:.2}s",
+        "{} Installing {} locked packages from {}...",
+        style("==>").cyan().bold(),
+        style(lockfile.formulas.len()).green().bold(),
+        lockfile_path.display()
+    );
+    let mut plan = zb_io::InstallPlan::from_lockfile(&lockfile)?;
+    plan.skip_link = unlinked(&brewfile, &plan);
+    install::execute_plan(installer, plan, !no_link).await?;
+
+    println!(
+        "{} Finished installing lockfile in {:.2}s",
         style("==>").cyan().bold(),
         start.elapsed().as_secs_f64()
     );
     Ok(())
 }
 
-fn load_manifest(path: &Path) -> Result<Vec<String>, zb_core::Error> {
+/// Write a Brewfile listing every formula installed on request, with tap formulas
+/// under their `user/repo/name` so they reinstall from the same tap
+pub fn dump(
+    installer: &mut zb_io::Installer,
+    manifest_path: &Path,
+    force: bool,
+) -> Result<(), zb_core::Error> {
+    if manifest_path.exists() && !force {
+        return Err(zb_core::Error::FileError {
+            message: format!(
+                "{} already exists (pass --force to overwrite it)",
+                manifest_path.display()
+            ),
+        });
+    }
+
+    let names: Vec<String> = installer
+        .list_installed()?
+        .into_iter()
+        .filter(|keg| keg.requested)
+        .map(|keg| keg.formula_name())
+        .collect();
+
+    std::fs::write(manifest_path, render_brewfile(&names)).map_err(|e| {
+        zb_core::Error::FileError {
+            message: format!(
+                "failed to write manifest {}: {}",
+                manifest_path.display(),
+                e
+            ),
+        }
+    })?;
+
+    println!(
+        "{} Wrote {} formulas to {}",
+        style("==>").cyan().bold(),
+        style(names.len()).green().bold(),
+        manifest_path.display()
+    );
+    Ok(())
+}
+
+/// Fail if anything the Brewfile lists isn't installed
+pub fn check(installer: &mut zb_io::Installer, manifest_path: &Path) -> Result<(), zb_core::Error> {
+    let brewfile = load_manifest(manifest_path)?;
+    let missing: Vec<String> = brewfile
+        .names()
+        .into_iter()
+        .filter(|name| installer.resolve_installed_name(name).is_none())
+        .collect();
+
+    if missing.is_empty() {
+        println!("The Brewfile's dependencies are satisfied.");
+        return Ok(());
+    }
+
+    println!(
+        "{} Missing from {}:",
+        style("==>").cyan().bold(),
+        manifest_path.display()
+    );
+    for name in &missing {
+        println!("    {} {}", style("✗").red(), name);
+    }
+    Err(zb_core::Error::ExecutionError {
+        message: format!(
+            "{} formulas in {} are not installed (run `zb bundle` to install them)",
+            missing.len(),
+            manifest_path.display()
+        ),
+    })
+}
+
+/// Uninstall formulas installed on request that the Brewfile doesn't list.
+/// Without `force` this only reports what would be removed.
+pub fn cleanup(
+    installer: &mut zb_io::Installer,
+    manifest_path: &Path,
+    force: bool,
+) -> Result<(), zb_core::Error> {
+    let brewfile = load_manifest(manifest_path)?;
+    let listed: Vec<String> = brewfile
+        .names()
+        .iter()
+        .filter_map(|name| installer.resolve_installed_name(name))
+        .collect();
+
+    // Formulas the Brewfile still needs as dependencies stay, even if they were requested once
+    let needed = if listed.is_empty() {
+        zb_core::DependencyGraph::new()
+    } else {
+        installer.installed_dependency_graph(&listed)?
+    };
+    let extra: Vec<String> = installer
+        .list_installed()?
+        .into_iter()
+        .filter(|keg| keg.requested && !needed.contains_key(&keg.name))
+        .map(|keg| keg.name)
+        .collect();
+
+    if extra.is_empty() {
+        println!("Nothing to clean up.");
+        return Ok(());
+    }
+
+    if !force {
+        println!(
+            "{} Would uninstall formulas not in {}:",
+            style("==>").cyan().bold(),
+            manifest_path.display()
+        );
+        for name in &extra {
+            println!("    {} {}", style("○").dim(), name);
+        }
+        println!("Run `zb bundle cleanup --force` to uninstall them.");
+        return Ok(());
+    }
+
+    println!(
+        "{} Uninstalling formulas not in {}...",
+        style("==>").cyan().bold(),
+        manifest_path.display()
+    );
+    for name in dependents_first(installer, extra)? {
+        print!("    {} {}...", style("○").dim(), name);
+        installer.uninstall(&name, false)?;
+        println!(" {}", style("✓").green());
+    }
+    println!("Run `zb autoremove` to remove dependencies they no longer need.");
+
+    Ok(())
+}
+
+/// Render names in Homebrew Bundle syntax, sorted
+fn render_brewfile(names: &[String]) -> String {
+    let mut names = names.to_vec();
+    names.sort();
+    names
+        .iter()
+        .map(|name| format!("brew \"{name}\"\n"))
+        .collect()
+}
+
+/// Read and parse a Brewfile, reporting the entries zerobrew can't install
+pub(crate) fn load_manifest(path: &Path) -> Result<Brewfile, zb_core::Error> {
     let contents = std::fs::read_to_string(path).map_err(|e| zb_core::Error::FileError {
         message: format!("failed to read manifest {}: {}", path.display(), e),
     })?;
 
-    let mut formulas = Vec::new();
-    let mut seen: HashSet<String> = HashSet::new();
+    let mut brewfile = parse_brewfile(&contents)?;
+    for formula in &mut brewfile.formulas {
+        formula.name = normalize_formula_name(&formula.name)?;
+    }
 
-    for line in contents.lines() {
-        // Handle inline comments by splitting on '#' and taking the first part
-        let entry = line.split('#').next().unwrap_or("").trim();
-        if entry.is_empty() {
-            continue;
-        }
+    let mut seen: HashSet<String> = HashSet::new();
+    brewfile.formulas.retain(|f| seen.insert(f.name.clone()));
 
-        if seen.insert(entry.to_string()) {
-            formulas.push(entry.to_string());
-        }
+    for skipped in &brewfile.skipped {
+        eprintln!(
+            "    Skipping {} (line {}: {})",
+            skipped.entry, skipped.line, skipped.reason
+        );
     }
 
-    if formulas.is_empty() {
+    if brewfile.formulas.is_empty() {
         return Err(zb_core::Error::FileError {
             message: format!("manifest {} did not contain any formulas", path.display()),
         });
     }
 
-    Ok(formulas)
+    Ok(brewfile)
 }
 
 #[cfg(test)]
@@ -74,7 +282,7 @@ mod tests {
         )
         .unwrap();
 
-        let entries = load_manifest(file.path()).unwrap();
+        let entries = load_manifest(file.path()).unwrap().names();
         assert_eq!(entries, vec!["jq", "wget", "git"]);
     }
 
@@ -87,7 +295,7 @@ mod tests {
         )
         .unwrap();
 
-        let entries = load_manifest(file.path()).unwrap();
+        let entries = load_manifest(file.path()).unwrap().names();
         assert_eq!(entries, vec!["jq", "wget", "git"]);
     }
 
@@ -118,4 +326,23 @@ mod tests {
             other => panic!("expected file error, got {other:?}"),
         }
     }
+
+    #[test]
+    fn dumped_brewfile_parses_back() {
+        let rendered = render_brewfile(&[
+            "wget".to_string(),
+            "openssl@3".to_string(),
+            "acme/tools/deployctl".to_string(),
+        ]);
+        assert_eq!(
+            rendered,
+            "brew \"acme/tools/deployctl\"\nbrew \"openssl@3\"\nbrew \"wget\"\n"
+        );
+
+        let parsed = parse_brewfile(&rendered).unwrap();
+        assert_eq!(
+            parsed.names(),
+            vec!["acme/tools/deployctl", "openssl@3", "wget"]
+        );
+    }
 }
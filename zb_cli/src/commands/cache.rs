@@ -0,0 +1,109 @@
+use console::style;
+use std::path::Path;
+use std::time::Instant;
+
+use zb_core::{BottleSelector, BottleTarget};
+
+use crate::utils::{normalize_formula_name, print_renamed};
+
+/// Bundle bottles and formula metadata into one archive for machines without
+/// network access. With no formulas, everything installed is exported.
+pub async fn export(
+    installer: &mut zb_io::Installer,
+    file: &Path,
+    formulas: Vec<String>,
+    target: Option<String>,
+) -> Result<(), zb_core::Error> {
+    let start = Instant::now();
+
+    let names = if formulas.is_empty() {
+        installer
+            .list_installed()?
+            .into_iter()
+            .map(|keg| keg.name)
+            .collect()
+    } else {
+        formulas
+            .iter()
+            .map(|f| normalize_formula_name(f))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    if names.is_empty() {
+        return Err(zb_core::Error::InvalidArgument {
+            message: "nothing to export; name formulas or install some first".to_string(),
+        });
+    }
+
+    let selector = match target {
+        Some(target) => BottleSelector::Target(target.parse::<BottleTarget>()?),
+        None => BottleSelector::host(),
+    };
+
+    println!(
+        "{} Resolving bottles for {}...",
+        style("==>").cyan().bold(),
+        style(&selector).bold()
+    );
+    let plan = installer.plan_for(&names, &selector).await?;
+    for (name, canonical) in &plan.renamed {
+        print_renamed(name, canonical);
+    }
+
+    println!(
+        "{} Exporting {} bottles to {}...",
+        style("==>").cyan().bold(),
+        plan.bottles.len(),
+        file.display()
+    );
+    let archive = installer.export_cache(&plan, file).await?;
+
+    for bottle in &archive.manifest.bottles {
+        println!(
+            "    {} {} {} {}",
+            style("✓").green(),
+            style(&bottle.name).bold(),
+            style(&bottle.version).dim(),
+            style(format!("({}, sha256 {})", bottle.tag, &bottle.sha256[..12])).dim()
+        );
+    }
+    println!(
+        "{} Exported {} bottles in {:.2}s",
+        style("==>").cyan().bold(),
+        style(archive.manifest.bottles.len()).green().bold(),
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
+
+/// Verify and load an exported archive into the local caches
+pub fn import(installer: &mut zb_io::Installer, file: &Path) -> Result<(), zb_core::Error> {
+    println!(
+        "{} Importing {}...",
+        style("==>").cyan().bold(),
+        file.display()
+    );
+    let (archive, added) = installer.import_cache(file)?;
+
+    for bottle in &archive.manifest.bottles {
+        println!(
+            "    {} {} {}",
+            style("✓").green(),
+            style(&bottle.name).bold(),
+            style(&bottle.version).dim()
+        );
+    }
+    println!(
+        "{} Imported {} bottles ({} new) and {} formulas",
+        style("==>").cyan().bold(),
+        style(archive.manifest.bottles.len()).green().bold(),
+        added,
+        archive.formulas.len()
+    );
+    println!(
+        "    Install them with {}",
+        style("zb install --offline <formula>").cyan()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeSet;
+
+use console::style;
+use zb_core::DependencyGraph;
+
+use crate::utils::{normalize_formula_name, resolve_installed_name};
+
+pub async fn execute(
+    installer: &mut zb_io::Installer,
+    formulas: Vec<String>,
+    tree: bool,
+    topological: bool,
+    installed: bool,
+    dot: bool,
+) -> Result<(), zb_core::Error> {
+    let mut roots = formulas
+        .iter()
+        .map(|f| normalize_formula_name(f))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Installed kegs answer from recorded edges; anything else asks the formula API
+    let graph = if installed {
+        roots = roots
+            .iter()
+            .map(|name| resolve_installed_name(installer, name))
+            .collect();
+        installer.installed_dependency_graph(&roots)?
+    } else {
+        installer.dependency_graph(&roots).await?
+    };
+
+    if dot {
+        print!("{}", render_dot(&graph));
+    } else if tree {
+        let roots = if roots.is_empty() {
+            graph.keys().cloned().collect()
+        } else {
+            roots
+        };
+        for root in &roots {
+            print!("{}", render_tree(&graph, root));
+        }
+    } else {
+        let order = if topological {
+            zb_core::topological_order(&graph)?
+        } else {
+            graph.keys().cloned().collect()
+        };
+        let roots: BTreeSet<&String> = roots.iter().collect();
+        for name in order.iter().filter(|name| !roots.contains(name)) {
+            println!("{name}");
+        }
+    }
+
+    Ok(())
+}
+
+fn render_tree(graph: &DependencyGraph, root: &str) -> String {
+    let mut out = format!("{}\n", style(root).bold());
+    let mut path = vec![root.to_string()];
+    render_children(graph, &mut path, "", &mut out);
+    out
+}
+
+fn render_children(
+    graph: &DependencyGraph,
+    path: &mut Vec<String>,
+    indent: &str,
+    out: &mut String,
+) {
+    let Some(deps) = path.last().and_then(|name| graph.get(name)) else {
+        return;
+    };
+    let deps = deps.clone();
+
+    for (i, dep) in deps.iter().enumerate() {
+        let last = i + 1 == deps.len();
+        let (branch, child_indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        out.push_str(&format!("{indent}{branch}{dep}\n"));
+
+        // Guard against cycles in recorded edges
+        if path.contains(dep) {
+            continue;
+        }
+        path.push(dep.clone());
+        render_children(graph, path, &format!("{indent}{child_indent}"), out);
+        path.pop();
+    }
+}
+
+fn render_dot(graph: &DependencyGraph) -> String {
+    let mut out = String::from("digraph dependencies {\n");
+    for (name, deps) in graph {
+        if deps.is_empty() {
+            out.push_str(&format!("  \"{name}\";\n"));
+        }
+        for dep in deps {
+            out.push_str(&format!("  \"{name}\" -> \"{dep}\";\n"));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> DependencyGraph {
+        let mut graph = DependencyGraph::new();
+        graph.insert(
+            "git".to_string(),
+            vec!["gettext".to_string(), "pcre2".to_string()],
+        );
+        graph.insert("gettext".to_string(), vec!["libiconv".to_string()]);
+        graph.insert("libiconv".to_string(), vec![]);
+        graph.insert("pcre2".to_string(), vec![]);
+        graph
+    }
+
+    #[test]
+    fn tree_draws_nested_branches() {
+        console::set_colors_enabled(false);
+        let rendered = render_tree(&graph(), "git");
+        assert_eq!(rendered, "git\n├── gettext\n│   └── libiconv\n└── pcre2\n");
+    }
+
+    #[test]
+    fn dot_lists_edges_and_leaf_nodes() {
+        let rendered = render_dot(&graph());
+        assert!(rendered.starts_with("digraph dependencies {\n"));
+        assert!(rendered.contains("  \"git\" -> \"gettext\";\n"));
+        assert!(rendered.contains("  \"pcre2\";\n"));
+        assert!(rendered.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,141 @@
+use console::style;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use zb_core::{BottleSelector, BottleTarget};
+use zb_io::{DownloadProgressCallback, InstallProgress};
+
+use crate::utils::{normalize_formula_name, print_renamed};
+
+/// Download and verify bottles into the cache without installing them,
+/// optionally for a platform other than this one. With `unpack`, bottles are
+/// also extracted into the store; kegs and links are never created.
+pub async fn execute(
+    installer: &mut zb_io::Installer,
+    formulas: Vec<String>,
+    target: Option<String>,
+    bottle_tag: Option<String>,
+    unpack: bool,
+) -> Result<(), zb_core::Error> {
+    let start = Instant::now();
+
+    let names = formulas
+        .iter()
+        .map(|f| normalize_formula_name(f))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let selector = match (target, bottle_tag) {
+        (_, Some(tag)) => BottleSelector::Tag(tag),
+        (Some(target), None) => BottleSelector::Target(target.parse::<BottleTarget>()?),
+        (None, None) => BottleSelector::host(),
+    };
+
+    println!(
+        "{} Resolving bottles for {}...",
+        style("==>").cyan().bold(),
+        style(&selector).bold()
+    );
+    let plan = installer.plan_for(&names, &selector).await?;
+    for (name, canonical) in &plan.renamed {
+        print_renamed(name, canonical);
+    }
+
+    println!("{} Downloading bottles...", style("==>").cyan().bold());
+    let (progress, bars) = download_progress();
+    let result = installer.fetch_bottles(&plan, Some(progress)).await;
+    for pb in bars.lock().unwrap().values() {
+        if !pb.is_finished() {
+            pb.finish_and_clear();
+        }
+    }
+    let blobs = result?;
+
+    if unpack {
+        println!("{} Unpacking into the store...", style("==>").cyan().bold());
+        installer.unpack_bottles(&plan, &blobs).await?;
+    }
+
+    for (formula, bottle) in plan.formulas.iter().zip(&plan.bottles) {
+        println!(
+            "    {} {} {} {}",
+            style("✓").green(),
+            style(&formula.name).bold(),
+            style(formula.effective_version()).dim(),
+            style(format!("({}, sha256 {})", bottle.tag, &bottle.sha256[..12])).dim()
+        );
+    }
+    println!(
+        "{} {} {} bottles in {:.2}s",
+        style("==>").cyan().bold(),
+        if unpack {
+            "Fetched and unpacked"
+        } else {
+            "Fetched"
+        },
+        style(plan.bottles.len()).green().bold(),
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
+
+type Bars = Arc<Mutex<HashMap<String, ProgressBar>>>;
+
+/// Per-bottle download bars; cached bottles never report and get no bar
+fn download_progress() -> (DownloadProgressCallback, Bars) {
+    let multi = MultiProgress::new();
+    let bars: Bars = Arc::new(Mutex::new(HashMap::new()));
+
+    let download_style = ProgressStyle::default_bar()
+        .template("    {prefix:<16} {bar:25.cyan/dim} {bytes:>10}/{total_bytes:<10} {eta:>6}")
+        .unwrap()
+        .progress_chars("━━╸");
+
+    let spinner_style = ProgressStyle::default_spinner()
+        .template("    {prefix:<16} {spinner:.cyan} {msg}")
+        .unwrap()
+        .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏");
+
+    let bars_clone = bars.clone();
+    let callback: DownloadProgressCallback = Arc::new(move |event| {
+        let mut bars = bars_clone.lock().unwrap();
+        match event {
+            InstallProgress::DownloadStarted { name, total_bytes } => {
+                let pb = if let Some(total) = total_bytes {
+                    let pb = multi.add(ProgressBar::new(total));
+                    pb.set_style(download_style.clone());
+                    pb
+                } else {
+                    let pb = multi.add(ProgressBar::new_spinner());
+                    pb.set_style(spinner_style.clone());
+                    pb.set_message("downloading...");
+                    pb.enable_steady_tick(std::time::Duration::from_millis(80));
+                    pb
+                };
+                pb.set_prefix(name.clone());
+                bars.insert(name, pb);
+            }
+            InstallProgress::DownloadProgress {
+                name,
+                downloaded,
+                total_bytes,
+            } => {
+                if let Some(pb) = bars.get(&name)
+                    && total_bytes.is_some()
+                {
+                    pb.set_position(downloaded);
+                }
+            }
+            InstallProgress::DownloadCompleted { name, .. } => {
+                if let Some(pb) = bars.get(&name) {
+                    pb.finish_and_clear();
+                }
+            }
+            _ => {}
+        }
+    });
+
+    (callback, bars)
+}
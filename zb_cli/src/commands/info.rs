@@ -1,11 +1,21 @@
 use chrono::{DateTime, Local};
 use console::style;
 
+use crate::utils::resolve_installed_name;
+
 pub fn execute(installer: &mut zb_io::Installer, formula: String) -> Result<(), zb_core::Error> {
-    if let Some(keg) = installer.get_installed(&formula) {
+    if let Some(keg) = installer.get_installed(&resolve_installed_name(installer, &formula)) {
         print_field("Name:", style(&keg.name).bold());
         print_field("Version:", &keg.version);
         print_field("Store key:", &keg.store_key[..12]);
+        print_field(
+            "Reason:",
+            if keg.requested {
+                "installed on request"
+            } else {
+                "installed as a dependency"
+            },
+        );
         print_field("Installed:", format_timestamp(keg.installed_at));
     } else {
         println!("Formula '{}' is not installed.", formula);
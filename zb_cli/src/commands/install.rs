@@ -1,26 +1,41 @@
 use console::style;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
-use zb_io::{InstallProgress, ProgressCallback};
+use zb_core::Formula;
+use zb_io::{ExecuteResult, InstallPlan, InstallProgress, ProgressCallback};
 
-use crate::utils::{normalize_formula_name, suggest_homebrew};
+use crate::utils::{normalize_formula_name, print_renamed, suggest_homebrew};
 
+/// Install formulas by name, bottle tarballs on disk (`./jq--1.7.1.arm64_sonoma.bottle.tar.gz`)
+/// and formula JSON files, resolving dependencies through the API
 pub async fn execute(
     installer: &mut zb_io::Installer,
     formulas: Vec<String>,
+    formula_json: Vec<PathBuf>,
     no_link: bool,
 ) -> Result<(), zb_core::Error> {
     let start = Instant::now();
+    let described: Vec<String> = formulas
+        .iter()
+        .cloned()
+        .chain(formula_json.iter().map(|p| p.display().to_string()))
+        .collect();
     println!(
         "{} Installing {}...",
         style("==>").cyan().bold(),
-        style(formulas.join(", ")).bold()
+        style(described.join(", ")).bold()
     );
 
     let mut normalized_names = Vec::new();
+    let mut provided = Vec::new();
     for formula in &formulas {
+        if is_bottle_path(formula) {
+            provided.push(installer.local_bottle_formula(Path::new(formula))?);
+            continue;
+        }
         match normalize_formula_name(formula) {
             Ok(name) => normalized_names.push(name),
             Err(e) => {
@@ -29,8 +44,14 @@ pub async fn execute(
             }
         }
     }
+    for path in &formula_json {
+        provided.push(read_formula_json(path)?);
+    }
 
-    let plan = match installer.plan(&normalized_names).await {
+    let plan = match installer
+        .plan_with_formulas(&normalized_names, provided)
+        .await
+    {
         Ok(p) => p,
         Err(e) => {
             for formula in &formulas {
@@ -40,6 +61,51 @@ pub async fn execute(
         }
     };
 
+    print_plan(&plan);
+
+    let result_val = execute_plan(installer, plan, !no_link).await;
+
+    let result = match result_val {
+        Ok(r) => r,
+        Err(e) => {
+            for formula in &formulas {
+                suggest_homebrew(formula, &e);
+            }
+            return Err(e);
+        }
+    };
+
+    let elapsed = start.elapsed();
+    println!();
+    println!(
+        "{} Installed {} packages in {:.2}s",
+        style("==>").cyan().bold(),
+        style(result.installed).green().bold(),
+        elapsed.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+/// Bottles are recognised by extension so that formula names never touch the filesystem
+fn is_bottle_path(arg: &str) -> bool {
+    arg.ends_with(".tar.gz")
+}
+
+fn read_formula_json(path: &Path) -> Result<Formula, zb_core::Error> {
+    let contents = std::fs::read_to_string(path).map_err(|e| zb_core::Error::FileError {
+        message: format!("failed to read {}: {e}", path.display()),
+    })?;
+    serde_json::from_str(&contents).map_err(|e| zb_core::Error::FileError {
+        message: format!("failed to parse formula JSON {}: {e}", path.display()),
+    })
+}
+
+/// List the packages a resolved plan will install
+pub(crate) fn print_plan(plan: &InstallPlan) {
+    for (name, canonical) in &plan.renamed {
+        print_renamed(name, canonical);
+    }
     println!(
         "{} Resolving dependencies ({} packages)...",
         style("==>").cyan().bold(),
@@ -52,7 +118,14 @@ pub async fn execute(
             style(&f.versions.stable).dim()
         );
     }
+}
 
+/// Download, unpack and link a resolved plan while rendering per-package progress bars
+pub(crate) async fn execute_plan(
+    installer: &mut zb_io::Installer,
+    plan: InstallPlan,
+    link: bool,
+) -> Result<ExecuteResult, zb_core::Error> {
     let multi = MultiProgress::new();
     let bars: Arc<Mutex<HashMap<String, ProgressBar>>> = Arc::new(Mutex::new(HashMap::new()));
 
@@ -150,8 +223,8 @@ pub async fn execute(
         }
     }));
 
-    let result_val = installer
-        .execute_with_progress(plan, !no_link, Some(progress_callback))
+    let result = installer
+        .execute_with_progress(plan, link, Some(progress_callback))
         .await;
 
     {
@@ -163,24 +236,5 @@ pub async fn execute(
         }
     }
 
-    let result = match result_val {
-        Ok(r) => r,
-        Err(e) => {
-            for formula in &formulas {
-                suggest_homebrew(formula, &e);
-            }
-            return Err(e);
-        }
-    };
-
-    let elapsed = start.elapsed();
-    println!();
-    println!(
-        "{} Installed {} packages in {:.2}s",
-        style("==>").cyan().bold(),
-        style(result.installed).green().bold(),
-        elapsed.as_secs_f64()
-    );
-
-    Ok(())
+    result
 }
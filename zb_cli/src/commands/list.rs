@@ -1,7 +1,10 @@
 use console::style;
 
-pub fn execute(installer: &mut zb_io::Installer) -> Result<(), zb_core::Error> {
-    let installed = installer.list_installed()?;
+pub fn execute(installer: &mut zb_io::Installer, leaves: bool) -> Result<(), zb_core::Error> {
+    let mut installed = installer.list_installed()?;
+    if leaves {
+        installed.retain(|keg| keg.requested);
+    }
 
     if installed.is_empty() {
         println!("No formulas installed.");
@@ -0,0 +1,66 @@
+use console::style;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use zb_core::Lockfile;
+
+use super::bundle::load_manifest;
+
+/// The lockfile lives next to the manifest it was resolved from
+pub fn lockfile_path(manifest_path: &Path) -> PathBuf {
+    manifest_path.with_file_name("zb.lock")
+}
+
+pub fn read_lockfile(path: &Path) -> Result<Lockfile, zb_core::Error> {
+    let contents = std::fs::read_to_string(path).map_err(|e| zb_core::Error::FileError {
+        message: format!(
+            "failed to read lockfile {}: {} (run `zb lock` first)",
+            path.display(),
+            e
+        ),
+    })?;
+    Lockfile::parse(&contents)
+}
+
+pub async fn execute(
+    installer: &mut zb_io::Installer,
+    manifest_path: &Path,
+) -> Result<(), zb_core::Error> {
+    let start = Instant::now();
+    let roots = load_manifest(manifest_path)?.names();
+
+    println!(
+        "{} Resolving {} formulas from {}...",
+        style("==>").cyan().bold(),
+        style(roots.len()).green().bold(),
+        manifest_path.display()
+    );
+    let plan = installer.plan(&roots).await?;
+
+    let mut lockfile = Lockfile::new(&roots, &plan.formulas, &plan.bottles);
+    lockfile.renamed = plan.renamed.clone();
+    let path = lockfile_path(manifest_path);
+    std::fs::write(&path, format!("{}\n", lockfile.to_json()?)).map_err(|e| {
+        zb_core::Error::FileError {
+            message: format!("failed to write lockfile {}: {}", path.display(), e),
+        }
+    })?;
+
+    for locked in &lockfile.formulas {
+        println!(
+            "    {} {} {}",
+            style(&locked.name).green(),
+            style(&locked.version).dim(),
+            style(&locked.bottle.tag).dim()
+        );
+    }
+    println!(
+        "{} Locked {} packages to {} in {:.2}s",
+        style("==>").cyan().bold(),
+        style(lockfile.formulas.len()).green().bold(),
+        path.display(),
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
@@ -1,11 +1,24 @@
+pub mod autoremove;
+pub mod bottle;
 pub mod bundle;
+pub mod cache;
 pub mod completion;
+pub mod deps;
+pub mod fetch;
 pub mod gc;
 pub mod info;
 pub mod init;
 pub mod install;
 pub mod list;
+pub mod lock;
 pub mod migrate;
+pub mod outdated;
 pub mod reset;
 pub mod run;
+pub mod search;
+pub mod serve;
+pub mod tap;
 pub mod uninstall;
+pub mod upgrade;
+pub mod uses;
+pub mod why;
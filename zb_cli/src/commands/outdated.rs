@@ -0,0 +1,51 @@
+use console::style;
+
+use crate::utils::{normalize_formula_name, resolve_installed_name};
+
+pub async fn execute(
+    installer: &mut zb_io::Installer,
+    formulas: Vec<String>,
+    json: bool,
+) -> Result<(), zb_core::Error> {
+    let names = formulas
+        .iter()
+        .map(|f| normalize_formula_name(f).map(|name| resolve_installed_name(installer, &name)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let outdated = installer.outdated(&names).await?;
+
+    if json {
+        let entries: Vec<_> = outdated
+            .iter()
+            .map(|pkg| {
+                serde_json::json!({
+                    "name": pkg.name,
+                    "installed_version": pkg.installed_version,
+                    "current_version": pkg.current_version,
+                })
+            })
+            .collect();
+        let rendered =
+            serde_json::to_string_pretty(&entries).map_err(|e| zb_core::Error::ExecutionError {
+                message: format!("failed to serialize outdated formulas: {e}"),
+            })?;
+        println!("{rendered}");
+        return Ok(());
+    }
+
+    if outdated.is_empty() {
+        println!("All formulas are up to date.");
+        return Ok(());
+    }
+
+    for pkg in &outdated {
+        println!(
+            "{} {} -> {}",
+            style(&pkg.name).bold(),
+            style(&pkg.installed_version).dim(),
+            style(&pkg.current_version).green()
+        );
+    }
+
+    Ok(())
+}
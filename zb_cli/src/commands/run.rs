@@ -4,7 +4,7 @@ use std::path::PathBuf;
 use std::process::Command;
 use zb_io::Installer;
 
-use crate::utils::normalize_formula_name;
+use crate::utils::{normalize_formula_name, resolve_installed_name};
 
 /// Prepare a package for execution by ensuring it's installed
 /// Returns the path to the executable
@@ -13,8 +13,9 @@ pub async fn prepare_execution(
     formula: &str,
 ) -> Result<PathBuf, zb_core::Error> {
     let normalized = normalize_formula_name(formula)?;
+    let mut name = resolve_installed_name(installer, &normalized);
 
-    let was_installed = installer.is_installed(&normalized);
+    let was_installed = installer.is_installed(&name);
 
     if !was_installed {
         println!(
@@ -24,24 +25,27 @@ pub async fn prepare_execution(
         );
 
         let plan = installer.plan(std::slice::from_ref(&normalized)).await?;
+        name = plan.canonical_name(&normalized).to_string();
         installer.execute(plan, false).await?;
     }
 
-    let installed =
-        installer
-            .get_installed(&normalized)
-            .ok_or_else(|| zb_core::Error::NotInstalled {
-                name: normalized.clone(),
-            })?;
+    let installed = installer
+        .get_installed(&name)
+        .ok_or_else(|| zb_core::Error::NotInstalled { name: name.clone() })?;
 
-    let keg_path = installer.keg_path(&normalized, &installed.version);
-    let bin_path = keg_path.join("bin").join(&normalized);
+    // Aliases often name the executable (`rg` for ripgrep); otherwise use the formula's own
+    let bin_dir = installer.keg_path(&name, &installed.version).join("bin");
+    let bin_path = if bin_dir.join(&normalized).exists() {
+        bin_dir.join(&normalized)
+    } else {
+        bin_dir.join(&name)
+    };
 
     if !bin_path.exists() {
         return Err(zb_core::Error::ExecutionError {
             message: format!(
                 "executable '{}' not found in package '{}'",
-                normalized, normalized
+                normalized, name
             ),
         });
     }
@@ -94,6 +98,8 @@ pub async fn execute(
         }
     }
 
+    // exec replaces the process, so uploads from installing the formula end here
+    installer.finish_uploads().await;
     let err = cmd.exec();
 
     Err(zb_core::Error::ExecutionError {
@@ -146,13 +152,7 @@ mod tests {
     }
 
     fn get_test_bottle_tag() -> &'static str {
-        if cfg!(target_os = "linux") {
-            "x86_64_linux"
-        } else if cfg!(target_arch = "x86_64") {
-            "sonoma"
-        } else {
-            "arm64_sonoma"
-        }
+        zb_core::BottleTarget::host().preferred_tags()[0]
     }
 
     #[tokio::test]
@@ -0,0 +1,33 @@
+use console::style;
+
+pub async fn execute(
+    installer: &mut zb_io::Installer,
+    query: String,
+    desc: bool,
+) -> Result<(), zb_core::Error> {
+    let results = installer.search(&query, desc).await?;
+
+    if results.is_empty() {
+        println!("No formulas found for {}.", style(&query).bold());
+        return Ok(());
+    }
+
+    for result in &results {
+        let marker = if result.installed {
+            format!(" {}", style("✓").green())
+        } else {
+            String::new()
+        };
+        println!(
+            "{} {}{}",
+            style(&result.name).bold(),
+            style(&result.version).dim(),
+            marker
+        );
+        if let Some(desc) = &result.desc {
+            println!("    {}", style(desc).dim());
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use console::style;
+use std::path::Path;
+
+use zb_io::ApiCache;
+
+/// Share this machine's bottle cache over HTTP as a mirror for
+/// `HOMEBREW_BOTTLE_MIRRORS`, optionally with cached formula JSON
+pub async fn execute(
+    installer: &mut zb_io::Installer,
+    root: &Path,
+    listen: &str,
+    formulas: bool,
+) -> Result<(), zb_core::Error> {
+    let api_cache = if formulas {
+        let cache = ApiCache::open(&root.join("cache/api.sqlite3")).map_err(|e| {
+            zb_core::Error::FileError {
+                message: format!("failed to open the formula cache: {e}"),
+            }
+        })?;
+        Some(cache)
+    } else {
+        None
+    };
+    let server = installer.mirror_server(api_cache);
+
+    let listener = tokio::net::TcpListener::bind(listen).await.map_err(|e| {
+        zb_core::Error::NetworkFailure {
+            message: format!("failed to listen on {listen}: {e}"),
+        }
+    })?;
+    let addr = listener
+        .local_addr()
+        .map_err(|e| zb_core::Error::NetworkFailure {
+            message: format!("failed to listen on {listen}: {e}"),
+        })?;
+
+    println!(
+        "{} Serving cached bottles on {}",
+        style("==>").cyan().bold(),
+        style(format!("http://{addr}")).bold()
+    );
+    println!(
+        "    Point other machines at it with {}",
+        style(format!("HOMEBREW_BOTTLE_MIRRORS=http://{addr}")).cyan()
+    );
+    if formulas {
+        println!(
+            "    Formula JSON is served from {}",
+            style(format!("http://{addr}/api/formula/<name>.json")).cyan()
+        );
+    }
+    println!("    Press Ctrl-C to stop");
+
+    server.serve(listener).await;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use console::style;
+
+/// Register a tap, or list the registered taps when no name is given
+pub fn execute(
+    installer: &mut zb_io::Installer,
+    name: Option<String>,
+    source: Option<String>,
+) -> Result<(), zb_core::Error> {
+    let Some(name) = name else {
+        for (name, source) in installer.list_taps()? {
+            println!("{} {}", style(&name).bold(), style(&source).dim());
+        }
+        return Ok(());
+    };
+
+    let Some(source) = source else {
+        return Err(zb_core::Error::InvalidArgument {
+            message: format!(
+                "pass the directory or URL serving {name}'s formula JSON, e.g. `zb tap {name} https://example.com/taps/{name}`"
+            ),
+        });
+    };
+
+    installer.add_tap(&name, &source)?;
+    println!(
+        "{} Tapped {} ({})",
+        style("==>").cyan().bold(),
+        style(&name).bold(),
+        source
+    );
+    Ok(())
+}
+
+pub fn untap(installer: &mut zb_io::Installer, name: String) -> Result<(), zb_core::Error> {
+    installer.remove_tap(&name)?;
+    println!(
+        "{} Untapped {}",
+        style("==>").cyan().bold(),
+        style(&name).bold()
+    );
+    Ok(())
+}
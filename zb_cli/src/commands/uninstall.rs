@@ -1,9 +1,12 @@
 use console::style;
 
+use crate::utils::resolve_installed_name;
+
 pub fn execute(
     installer: &mut zb_io::Installer,
     formulas: Vec<String>,
     all: bool,
+    ignore_dependencies: bool,
 ) -> Result<(), zb_core::Error> {
     let formulas = if all {
         let installed = installer.list_installed()?;
@@ -14,6 +17,17 @@ pub fn execute(
         installed.into_iter().map(|k| k.name).collect()
     } else {
         formulas
+            .iter()
+            .map(|name| resolve_installed_name(installer, name))
+            .collect()
+    };
+
+    // Removing everything can't leave a dependent behind
+    let ignore_dependencies = ignore_dependencies || all;
+    let formulas = if ignore_dependencies {
+        formulas
+    } else {
+        dependents_first(installer, formulas)?
     };
 
     println!(
@@ -27,7 +41,7 @@ pub fn execute(
     if formulas.len() > 1 {
         for name in &formulas {
             print!("    {} {}...", style("○").dim(), name);
-            match installer.uninstall(name) {
+            match installer.uninstall(name, ignore_dependencies) {
                 Ok(()) => println!(" {}", style("✓").green()),
                 Err(e) => {
                     println!(" {}", style("✗").red());
@@ -35,7 +49,7 @@ pub fn execute(
                 }
             }
         }
-    } else if let Err(e) = installer.uninstall(&formulas[0]) {
+    } else if let Err(e) = installer.uninstall(&formulas[0], ignore_dependencies) {
         errors.push((formulas[0].clone(), e));
     }
 
@@ -54,3 +68,32 @@ pub fn execute(
         Err(errors.remove(0).1)
     }
 }
+
+/// Order formulas so each one is removed before the formulas it depends on
+pub(crate) fn dependents_first(
+    installer: &zb_io::Installer,
+    mut remaining: Vec<String>,
+) -> Result<Vec<String>, zb_core::Error> {
+    let mut ordered = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        let mut ready = Vec::new();
+        for name in &remaining {
+            let dependents = installer.dependents(name)?;
+            if !dependents.iter().any(|d| remaining.contains(d)) {
+                ready.push(name.clone());
+            }
+        }
+
+        // Nothing left can go first; let uninstall report the dependents
+        if ready.is_empty() {
+            ordered.append(&mut remaining);
+            break;
+        }
+
+        remaining.retain(|name| !ready.contains(name));
+        ordered.extend(ready);
+    }
+
+    Ok(ordered)
+}
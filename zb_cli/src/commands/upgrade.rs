@@ -0,0 +1,63 @@
+use console::style;
+use std::time::Instant;
+
+use super::install::execute_plan;
+use crate::utils::{normalize_formula_name, resolve_installed_name};
+
+pub async fn execute(
+    installer: &mut zb_io::Installer,
+    formulas: Vec<String>,
+) -> Result<(), zb_core::Error> {
+    let start = Instant::now();
+
+    let names = formulas
+        .iter()
+        .map(|f| normalize_formula_name(f).map(|name| resolve_installed_name(installer, &name)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!(
+        "{} Checking for outdated formulas...",
+        style("==>").cyan().bold()
+    );
+    let outdated = installer.outdated(&names).await?;
+
+    if outdated.is_empty() {
+        if names.is_empty() {
+            println!("All formulas are up to date.");
+        } else {
+            println!("{} already up to date.", names.join(", "));
+        }
+        return Ok(());
+    }
+
+    println!(
+        "{} Upgrading {} formulas:",
+        style("==>").cyan().bold(),
+        style(outdated.len()).green().bold()
+    );
+    for pkg in &outdated {
+        println!(
+            "    {} {} -> {}",
+            style(&pkg.name).green(),
+            style(&pkg.installed_version).dim(),
+            pkg.current_version
+        );
+    }
+
+    let upgrade_names: Vec<String> = outdated.into_iter().map(|pkg| pkg.name).collect();
+    let mut plan = installer.plan(&upgrade_names).await?;
+    // Upgrading must not turn dependencies into requested packages
+    plan.requested.clear();
+    let result = execute_plan(installer, plan, true).await?;
+
+    println!();
+    println!(
+        "{} Upgraded {} formulas ({} packages processed) in {:.2}s",
+        style("==>").cyan().bold(),
+        style(upgrade_names.len()).green().bold(),
+        result.installed,
+        start.elapsed().as_secs_f64()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use console::style;
+
+use crate::utils::{normalize_formula_name, resolve_installed_name};
+
+pub fn execute(installer: &mut zb_io::Installer, formula: String) -> Result<(), zb_core::Error> {
+    let name = resolve_installed_name(installer, &normalize_formula_name(&formula)?);
+    let users = installer.uses(&name)?;
+
+    if users.is_empty() {
+        println!("No installed formulas depend on {}.", style(&name).bold());
+        return Ok(());
+    }
+
+    let direct = installer.dependents(&name)?;
+    for user in &users {
+        if direct.contains(user) {
+            println!("{}", style(user).bold());
+        } else {
+            println!("{} {}", style(user).bold(), style("(indirect)").dim());
+        }
+    }
+
+    Ok(())
+}
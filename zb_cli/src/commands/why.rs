@@ -0,0 +1,27 @@
+use console::style;
+
+use crate::utils::{normalize_formula_name, resolve_installed_name};
+
+pub fn execute(installer: &mut zb_io::Installer, formula: String) -> Result<(), zb_core::Error> {
+    let name = resolve_installed_name(installer, &normalize_formula_name(&formula)?);
+    let chains = installer.why(&name)?;
+
+    if chains.is_empty() {
+        println!(
+            "{} is not needed by any requested formula (see `zb autoremove`).",
+            style(&name).bold()
+        );
+        return Ok(());
+    }
+
+    for chain in &chains {
+        if chain.len() == 1 {
+            println!("{} was installed on request", style(&chain[0]).bold());
+        } else {
+            let rendered: Vec<String> = chain.iter().map(|n| style(n).bold().to_string()).collect();
+            println!("{}", rendered.join(" -> "));
+        }
+    }
+
+    Ok(())
+}
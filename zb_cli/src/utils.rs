@@ -12,6 +12,10 @@ pub fn normalize_formula_name(name: &str) -> Result<String, zb_core::Error> {
             }
             return Ok(formula.to_string());
         }
+        // Resolved through a registered tap, or rejected there if the tap is unknown
+        if zb_core::split_tap_name(trimmed).is_some() {
+            return Ok(trimmed.to_string());
+        }
         return Err(zb_core::Error::UnsupportedTap {
             name: trimmed.to_string(),
         });
@@ -20,6 +24,31 @@ pub fn normalize_formula_name(name: &str) -> Result<String, zb_core::Error> {
     Ok(trimmed.to_string())
 }
 
+/// Follow an alias or old name to the installed keg it refers to, telling the user
+/// which name was chosen. Names that match no installed keg are returned unchanged.
+pub fn resolve_installed_name(installer: &zb_io::Installer, name: &str) -> String {
+    match installer.resolve_installed_name(name) {
+        // `user/repo/name` finding its keg isn't worth a note
+        Some(canonical) if zb_core::split_tap_name(name).is_some_and(|(_, f)| f == canonical) => {
+            canonical
+        }
+        Some(canonical) if canonical != name => {
+            print_renamed(name, &canonical);
+            canonical
+        }
+        _ => name.to_string(),
+    }
+}
+
+pub fn print_renamed(name: &str, canonical: &str) {
+    eprintln!(
+        "{} {} resolved to {}",
+        style("==>").cyan().bold(),
+        style(name).bold(),
+        style(canonical).bold()
+    );
+}
+
 pub fn suggest_homebrew(formula: &str, error: &zb_core::Error) {
     eprintln!();
     eprintln!(
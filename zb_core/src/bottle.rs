@@ -1,114 +1,274 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{Error, Formula};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Where Homebrew's own bottles are published; `HOMEBREW_BOTTLE_DOMAIN` replaces this root
+pub const DEFAULT_BOTTLE_DOMAIN: &str = "https://ghcr.io/v2/homebrew/core";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SelectedBottle {
     pub tag: String,
     pub url: String,
     pub sha256: String,
 }
 
-pub fn select_bottle(formula: &Formula) -> Result<SelectedBottle, Error> {
-    // Prefer macOS ARM bottles in order of preference (newest first)
-    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    {
-        let macos_tags = [
-            "arm64_tahoe",
-            "arm64_sequoia",
-            "arm64_sonoma",
-            "arm64_ventura",
-        ];
-
-        for preferred_tag in macos_tags {
-            if let Some(file) = formula.bottle.stable.files.get(preferred_tag) {
-                return Ok(SelectedBottle {
-                    tag: preferred_tag.to_string(),
-                    url: file.url.clone(),
-                    sha256: file.sha256.clone(),
-                });
-            }
+impl SelectedBottle {
+    /// The URL of this bottle of `formula` when Homebrew's bottles are served from
+    /// `domain`, using Homebrew's layout: another GitHub Packages registry keeps the
+    /// `{name}/blobs/sha256:{sha256}` paths, anything else serves files named
+    /// `{name}-{version}.{tag}.bottle[.{rebuild}].tar.gz`. Bottles published
+    /// elsewhere, such as by taps, keep their URL.
+    pub fn url_for_domain(&self, formula: &Formula, domain: &str) -> String {
+        let domain = domain.trim_end_matches('/');
+        let Some(path) = self
+            .url
+            .strip_prefix(DEFAULT_BOTTLE_DOMAIN)
+            .and_then(|rest| rest.strip_prefix('/'))
+        else {
+            return self.url.clone();
+        };
+
+        if domain.starts_with("https://ghcr.io/v2/") {
+            return format!("{domain}/{path}");
         }
+
+        let rebuild = match formula.bottle.stable.rebuild {
+            0 => String::new(),
+            rebuild => format!(".{rebuild}"),
+        };
+        let file_name = format!(
+            "{}-{}.{}.bottle{rebuild}.tar.gz",
+            formula.name,
+            formula.effective_version(),
+            self.tag
+        );
+        format!("{domain}/{}", url_encode(&file_name))
     }
+}
 
-    // Prefer macOS Intel bottles in order of preference (newest first)
-    // Homebrew uses bare OS version names (e.g. "sonoma") for Intel Mac bottles
-    #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-    {
-        let macos_tags = ["tahoe", "sequoia", "sonoma", "ventura"];
-
-        for preferred_tag in macos_tags {
-            if let Some(file) = formula.bottle.stable.files.get(preferred_tag) {
-                return Ok(SelectedBottle {
-                    tag: preferred_tag.to_string(),
-                    url: file.url.clone(),
-                    sha256: file.sha256.clone(),
-                });
+/// Percent-encode everything but unreserved characters, as Ruby's `ERB::Util.url_encode`
+/// does for Homebrew's bottle file names (`openssl@3` becomes `openssl%403`)
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.' | b'~') {
+                (b as char).to_string()
+            } else {
+                format!("%{b:02X}")
             }
+        })
+        .collect()
+}
+
+/// The platform a bottle is selected for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BottleTarget {
+    pub os: TargetOs,
+    pub arch: TargetArch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetOs {
+    MacOs,
+    Linux,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetArch {
+    Aarch64,
+    X86_64,
+}
+
+impl BottleTarget {
+    pub const fn new(os: TargetOs, arch: TargetArch) -> Self {
+        Self { os, arch }
+    }
+
+    /// The platform zerobrew is running on
+    pub fn host() -> Self {
+        let os = if cfg!(target_os = "linux") {
+            TargetOs::Linux
+        } else {
+            TargetOs::MacOs
+        };
+        let arch = if cfg!(target_arch = "x86_64") {
+            TargetArch::X86_64
+        } else {
+            TargetArch::Aarch64
+        };
+        Self { os, arch }
+    }
+
+    /// Bottle tags built for this target, most preferred (newest) first.
+    /// The arch-independent `all` tag is accepted after these on every target.
+    pub fn preferred_tags(&self) -> &'static [&'static str] {
+        match (self.os, self.arch) {
+            (TargetOs::MacOs, TargetArch::Aarch64) => &[
+                "arm64_tahoe",
+                "arm64_sequoia",
+                "arm64_sonoma",
+                "arm64_ventura",
+            ],
+            // Homebrew uses bare OS version names (e.g. "sonoma") for Intel Mac bottles
+            (TargetOs::MacOs, TargetArch::X86_64) => &["tahoe", "sequoia", "sonoma", "ventura"],
+            (TargetOs::Linux, TargetArch::Aarch64) => &["arm64_linux"],
+            (TargetOs::Linux, TargetArch::X86_64) => &["x86_64_linux"],
         }
     }
 
-    // Prefer Linux x86_64 bottles
-    #[cfg(target_os = "linux")]
-    {
-        let linux_tags = ["x86_64_linux"];
-        for preferred_tag in linux_tags {
-            if let Some(file) = formula.bottle.stable.files.get(preferred_tag) {
-                return Ok(SelectedBottle {
-                    tag: preferred_tag.to_string(),
-                    url: file.url.clone(),
-                    sha256: file.sha256.clone(),
-                });
-            }
+    /// The bottle tag for this target on an OS release, such as `15.3` from macOS's
+    /// `sw_vers -productVersion`. Linux bottles aren't tied to a release.
+    pub fn tag_for_release(&self, release: &str) -> Option<String> {
+        if self.os == TargetOs::Linux {
+            return Some(self.preferred_tags()[0].to_string());
         }
+
+        let mut parts = release.trim().split('.');
+        let major: u32 = parts.next()?.parse().ok()?;
+        let minor: u32 = parts.next().and_then(|m| m.parse().ok()).unwrap_or(0);
+        let name = match (major, minor) {
+            (26, _) => "tahoe",
+            (15, _) => "sequoia",
+            (14, _) => "sonoma",
+            (13, _) => "ventura",
+            (12, _) => "monterey",
+            (11, _) => "big_sur",
+            (10, 15) if self.arch == TargetArch::X86_64 => "catalina",
+            _ => return None,
+        };
+        Some(match self.arch {
+            TargetArch::Aarch64 => format!("arm64_{name}"),
+            TargetArch::X86_64 => name.to_string(),
+        })
     }
 
-    // Check for universal "all" bottle (platform-independent packages like ca-certificates)
-    if let Some(file) = formula.bottle.stable.files.get("all") {
-        return Ok(SelectedBottle {
-            tag: "all".to_string(),
-            url: file.url.clone(),
-            sha256: file.sha256.clone(),
-        });
+    /// Whether a tag outside the preference list (an older or newer macOS release)
+    /// was still built for this target
+    fn accepts_other_tag(&self, tag: &str) -> bool {
+        if tag == "all" || tag.contains("linux") {
+            return false;
+        }
+        match (self.os, self.arch) {
+            (TargetOs::MacOs, TargetArch::Aarch64) => tag.starts_with("arm64_"),
+            (TargetOs::MacOs, TargetArch::X86_64) => !tag.starts_with("arm64_"),
+            (TargetOs::Linux, _) => false,
+        }
     }
+}
+
+impl std::str::FromStr for BottleTarget {
+    type Err = Error;
+
+    /// Parse a Rust target triple (`aarch64-apple-darwin`, `x86_64-unknown-linux-gnu`)
+    /// or an `os-arch` shorthand (`macos-arm64`, `linux-x86_64`)
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let parts: Vec<&str> = s.split('-').collect();
+        let os = parts.iter().find_map(|part| match *part {
+            "apple" | "darwin" | "macos" => Some(TargetOs::MacOs),
+            "linux" => Some(TargetOs::Linux),
+            _ => None,
+        });
+        let arch = parts.iter().find_map(|part| match *part {
+            "aarch64" | "arm64" => Some(TargetArch::Aarch64),
+            "x86_64" | "amd64" => Some(TargetArch::X86_64),
+            _ => None,
+        });
 
-    // Fallback: any arm64 macOS bottle (but not linux)
-    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    for (tag, file) in &formula.bottle.stable.files {
-        if tag.starts_with("arm64_") && !tag.contains("linux") {
-            return Ok(SelectedBottle {
-                tag: tag.clone(),
-                url: file.url.clone(),
-                sha256: file.sha256.clone(),
-            });
+        match (os, arch) {
+            (Some(os), Some(arch)) => Ok(Self { os, arch }),
+            _ => Err(Error::InvalidArgument {
+                message: format!(
+                    "unknown target '{s}' (expected e.g. aarch64-apple-darwin or linux-x86_64)"
+                ),
+            }),
         }
     }
+}
+
+/// How to choose among a formula's bottles
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BottleSelector {
+    /// The best bottle for a platform
+    Target(BottleTarget),
+    /// Exactly this tag, falling back to a universal `all` bottle
+    Tag(String),
+}
+
+impl BottleSelector {
+    pub fn host() -> Self {
+        Self::Target(BottleTarget::host())
+    }
 
-    // Fallback: any Intel macOS bottle (bare OS name, not arm64_ or linux)
-    #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-    for (tag, file) in &formula.bottle.stable.files {
-        if !tag.starts_with("arm64_") && !tag.contains("linux") && tag != "all" {
-            return Ok(SelectedBottle {
-                tag: tag.clone(),
-                url: file.url.clone(),
-                sha256: file.sha256.clone(),
-            });
+    pub fn select(&self, formula: &Formula) -> Result<SelectedBottle, Error> {
+        match self {
+            Self::Target(target) => select_bottle_for(formula, target),
+            Self::Tag(tag) => {
+                let files = &formula.bottle.stable.files;
+                files
+                    .get_key_value(tag.as_str())
+                    .or_else(|| files.get_key_value("all"))
+                    .map(|(tag, file)| SelectedBottle {
+                        tag: tag.clone(),
+                        url: file.url.clone(),
+                        sha256: file.sha256.clone(),
+                    })
+                    .ok_or_else(|| Error::UnsupportedBottle {
+                        name: formula.name.clone(),
+                    })
+            }
         }
     }
+}
 
-    // Fallback for Linux: any linux bottle
-    #[cfg(target_os = "linux")]
-    for (tag, file) in &formula.bottle.stable.files {
-        if tag.contains("linux") {
-            return Ok(SelectedBottle {
-                tag: tag.clone(),
-                url: file.url.clone(),
-                sha256: file.sha256.clone(),
-            });
+impl std::fmt::Display for BottleSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Target(target) => {
+                let os = match target.os {
+                    TargetOs::MacOs => "macos",
+                    TargetOs::Linux => "linux",
+                };
+                let arch = match target.arch {
+                    TargetArch::Aarch64 => "arm64",
+                    TargetArch::X86_64 => "x86_64",
+                };
+                write!(f, "{os}-{arch}")
+            }
+            Self::Tag(tag) => write!(f, "{tag}"),
         }
     }
+}
 
-    Err(Error::UnsupportedBottle {
-        name: formula.name.clone(),
-    })
+pub fn select_bottle(formula: &Formula) -> Result<SelectedBottle, Error> {
+    select_bottle_for(formula, &BottleTarget::host())
+}
+
+/// Pick the best bottle for `target`: a preferred tag, then a universal `all`
+/// bottle, then any other bottle built for the same OS and architecture
+pub fn select_bottle_for(
+    formula: &Formula,
+    target: &BottleTarget,
+) -> Result<SelectedBottle, Error> {
+    let files = &formula.bottle.stable.files;
+
+    let ranked = target
+        .preferred_tags()
+        .iter()
+        .chain(&["all"])
+        .find_map(|tag| files.get_key_value(*tag));
+    let fallback = || files.iter().find(|(tag, _)| target.accepts_other_tag(tag));
+
+    match ranked.or_else(fallback) {
+        Some((tag, file)) => Ok(SelectedBottle {
+            tag: tag.clone(),
+            url: file.url.clone(),
+            sha256: file.sha256.clone(),
+        }),
+        None => Err(Error::UnsupportedBottle {
+            name: formula.name.clone(),
+        }),
+    }
 }
 
 #[cfg(test)]
@@ -117,143 +277,264 @@ mod tests {
     use crate::formula::{Bottle, BottleFile, BottleStable, Versions};
     use std::collections::BTreeMap;
 
+    const MACOS_ARM: BottleTarget = BottleTarget::new(TargetOs::MacOs, TargetArch::Aarch64);
+    const MACOS_INTEL: BottleTarget = BottleTarget::new(TargetOs::MacOs, TargetArch::X86_64);
+    const LINUX_ARM: BottleTarget = BottleTarget::new(TargetOs::Linux, TargetArch::Aarch64);
+    const LINUX_INTEL: BottleTarget = BottleTarget::new(TargetOs::Linux, TargetArch::X86_64);
+
+    fn formula_with_tags(name: &str, tags: &[&str]) -> Formula {
+        let files: BTreeMap<String, BottleFile> = tags
+            .iter()
+            .map(|tag| {
+                (
+                    tag.to_string(),
+                    BottleFile {
+                        url: format!("https://example.com/{name}.{tag}.bottle.tar.gz"),
+                        sha256: "abc123".to_string(),
+                    },
+                )
+            })
+            .collect();
+
+        Formula {
+            name: name.to_string(),
+            versions: Versions {
+                stable: "1.0.0".to_string(),
+            },
+            dependencies: Vec::new(),
+            bottle: Bottle {
+                stable: BottleStable { files, rebuild: 0 },
+            },
+            revision: 0,
+            desc: None,
+            aliases: Vec::new(),
+            oldnames: Vec::new(),
+        }
+    }
+
+    fn selected_tag(formula: &Formula, target: BottleTarget) -> Option<String> {
+        select_bottle_for(formula, &target).ok().map(|b| b.tag)
+    }
+
     #[test]
     fn selects_platform_bottle() {
         let fixture = include_str!("../fixtures/formula_foo.json");
         let formula: Formula = serde_json::from_str(fixture).unwrap();
 
-        let selected = select_bottle(&formula).unwrap();
-
-        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-        {
-            assert_eq!(selected.tag, "arm64_sonoma");
-            assert_eq!(
-                selected.url,
-                "https://example.com/foo-1.2.3.arm64_sonoma.bottle.tar.gz"
-            );
-            assert_eq!(
-                selected.sha256,
-                "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
-            );
-        }
+        let selected = select_bottle_for(&formula, &MACOS_ARM).unwrap();
+        assert_eq!(selected.tag, "arm64_sonoma");
+        assert_eq!(
+            selected.url,
+            "https://example.com/foo-1.2.3.arm64_sonoma.bottle.tar.gz"
+        );
+        assert_eq!(
+            selected.sha256,
+            "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+        );
 
-        #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-        {
-            assert_eq!(selected.tag, "sonoma");
-            assert_eq!(
-                selected.url,
-                "https://example.com/foo-1.2.3.sonoma.bottle.tar.gz"
-            );
-            assert_eq!(
-                selected.sha256,
-                "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc"
-            );
-        }
+        let selected = select_bottle_for(&formula, &MACOS_INTEL).unwrap();
+        assert_eq!(selected.tag, "sonoma");
+        assert_eq!(
+            selected.sha256,
+            "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc"
+        );
 
-        #[cfg(target_os = "linux")]
-        {
-            assert_eq!(selected.tag, "x86_64_linux");
-            assert_eq!(
-                selected.url,
-                "https://example.com/foo-1.2.3.x86_64_linux.bottle.tar.gz"
-            );
-            assert_eq!(
-                selected.sha256,
-                "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
-            );
-        }
+        let selected = select_bottle_for(&formula, &LINUX_INTEL).unwrap();
+        assert_eq!(selected.tag, "x86_64_linux");
+        assert_eq!(
+            selected.sha256,
+            "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
+        );
+
+        // An x86_64 Linux bottle is no use on ARM Linux
+        assert_eq!(selected_tag(&formula, LINUX_ARM), None);
+    }
+
+    #[test]
+    fn host_selection_uses_host_target() {
+        let fixture = include_str!("../fixtures/formula_foo.json");
+        let formula: Formula = serde_json::from_str(fixture).unwrap();
+        assert_eq!(
+            select_bottle(&formula).ok(),
+            select_bottle_for(&formula, &BottleTarget::host()).ok()
+        );
+    }
+
+    #[test]
+    fn prefers_newest_release_for_each_target() {
+        let formula = formula_with_tags(
+            "multi",
+            &[
+                "arm64_sequoia",
+                "arm64_sonoma",
+                "arm64_linux",
+                "sequoia",
+                "sonoma",
+                "x86_64_linux",
+                "all",
+            ],
+        );
+
+        assert_eq!(
+            selected_tag(&formula, MACOS_ARM).as_deref(),
+            Some("arm64_sequoia")
+        );
+        assert_eq!(
+            selected_tag(&formula, MACOS_INTEL).as_deref(),
+            Some("sequoia")
+        );
+        assert_eq!(
+            selected_tag(&formula, LINUX_ARM).as_deref(),
+            Some("arm64_linux")
+        );
+        assert_eq!(
+            selected_tag(&formula, LINUX_INTEL).as_deref(),
+            Some("x86_64_linux")
+        );
     }
 
     #[test]
     fn selects_all_bottle_for_universal_packages() {
-        let mut files = BTreeMap::new();
-        files.insert(
-            "all".to_string(),
-            BottleFile {
-                url: "https://ghcr.io/v2/homebrew/core/ca-certificates/blobs/sha256:abc123"
-                    .to_string(),
-                sha256: "abc123".to_string(),
-            },
+        let formula = formula_with_tags("ca-certificates", &["all"]);
+
+        for target in [MACOS_ARM, MACOS_INTEL, LINUX_ARM, LINUX_INTEL] {
+            let selected = select_bottle_for(&formula, &target).unwrap();
+            assert_eq!(selected.tag, "all");
+            assert!(selected.url.contains("ca-certificates"));
+        }
+    }
+
+    #[test]
+    fn prefers_all_over_unlisted_releases() {
+        let formula = formula_with_tags("scripts", &["all", "arm64_monterey", "monterey"]);
+        assert_eq!(selected_tag(&formula, MACOS_ARM).as_deref(), Some("all"));
+
+        let formula = formula_with_tags("old", &["arm64_monterey", "monterey", "x86_64_linux"]);
+        assert_eq!(
+            selected_tag(&formula, MACOS_ARM).as_deref(),
+            Some("arm64_monterey")
         );
+        assert_eq!(
+            selected_tag(&formula, MACOS_INTEL).as_deref(),
+            Some("monterey")
+        );
+        assert_eq!(selected_tag(&formula, LINUX_ARM), None);
+    }
 
-        let formula = Formula {
-            name: "ca-certificates".to_string(),
-            versions: Versions {
-                stable: "2024-01-01".to_string(),
-            },
-            dependencies: Vec::new(),
-            bottle: Bottle {
-                stable: BottleStable { files, rebuild: 0 },
-            },
-            revision: 0,
-        };
+    #[test]
+    fn parses_target_descriptions() {
+        assert_eq!(
+            "aarch64-apple-darwin".parse::<BottleTarget>().unwrap(),
+            MACOS_ARM
+        );
+        assert_eq!(
+            "x86_64-apple-darwin".parse::<BottleTarget>().unwrap(),
+            MACOS_INTEL
+        );
+        assert_eq!(
+            "aarch64-unknown-linux-gnu".parse::<BottleTarget>().unwrap(),
+            LINUX_ARM
+        );
+        assert_eq!("linux-x86_64".parse::<BottleTarget>().unwrap(), LINUX_INTEL);
+        assert_eq!("macos-arm64".parse::<BottleTarget>().unwrap(), MACOS_ARM);
+        assert!("riscv64-linux".parse::<BottleTarget>().is_err());
+        assert!("arm64".parse::<BottleTarget>().is_err());
+    }
 
-        let selected = select_bottle(&formula).unwrap();
-        assert_eq!(selected.tag, "all");
-        assert!(selected.url.contains("ca-certificates"));
+    #[test]
+    fn derives_tags_from_the_os_release() {
+        assert_eq!(
+            MACOS_ARM.tag_for_release("14.6.1").as_deref(),
+            Some("arm64_sonoma")
+        );
+        assert_eq!(
+            MACOS_INTEL.tag_for_release("10.15.7").as_deref(),
+            Some("catalina")
+        );
+        assert_eq!(MACOS_ARM.tag_for_release("10.15.7"), None);
+        assert_eq!(MACOS_ARM.tag_for_release("27.0"), None);
+        assert_eq!(MACOS_ARM.tag_for_release(""), None);
+        assert_eq!(
+            LINUX_ARM.tag_for_release("").as_deref(),
+            Some("arm64_linux")
+        );
     }
 
     #[test]
-    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    fn errors_when_no_arm64_bottle() {
-        let mut files = BTreeMap::new();
-        files.insert(
-            "sonoma".to_string(),
-            BottleFile {
-                url: "https://example.com/legacy.tar.gz".to_string(),
-                sha256: "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc"
-                    .to_string(),
-            },
+    fn selector_picks_exact_tag_or_all() {
+        let formula = formula_with_tags("foo", &["arm64_sonoma", "x86_64_linux"]);
+        let selected = BottleSelector::Tag("arm64_sonoma".to_string())
+            .select(&formula)
+            .unwrap();
+        assert_eq!(selected.tag, "arm64_sonoma");
+        assert!(
+            BottleSelector::Tag("arm64_sequoia".to_string())
+                .select(&formula)
+                .is_err()
         );
 
-        let formula = Formula {
-            name: "legacy".to_string(),
-            versions: Versions {
-                stable: "0.1.0".to_string(),
-            },
-            dependencies: Vec::new(),
-            bottle: Bottle {
-                stable: BottleStable { files, rebuild: 0 },
-            },
-            revision: 0,
-        };
+        let formula = formula_with_tags("scripts", &["all"]);
+        let selected = BottleSelector::Tag("arm64_sequoia".to_string())
+            .select(&formula)
+            .unwrap();
+        assert_eq!(selected.tag, "all");
 
-        let err = select_bottle(&formula).unwrap_err();
-        assert!(matches!(
-            err,
-            Error::UnsupportedBottle { name } if name == "legacy"
-        ));
+        let selector = BottleSelector::Target(LINUX_ARM);
+        assert_eq!(selector.to_string(), "linux-arm64");
+        assert_eq!(selector.select(&formula).unwrap().tag, "all");
     }
 
     #[test]
-    #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-    fn errors_when_no_x86_64_bottle() {
-        let mut files = BTreeMap::new();
-        files.insert(
-            "arm64_sonoma".to_string(),
-            BottleFile {
-                url: "https://example.com/legacy.tar.gz".to_string(),
-                sha256: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
-                    .to_string(),
-            },
+    fn rewrites_homebrew_bottle_urls_for_a_bottle_domain() {
+        let mut formula = formula_with_tags("openssl@3", &[]);
+        formula.revision = 1;
+        let bottle = SelectedBottle {
+            tag: "arm64_sonoma".to_string(),
+            url: format!("{DEFAULT_BOTTLE_DOMAIN}/openssl/3/blobs/sha256:abc123"),
+            sha256: "abc123".to_string(),
+        };
+
+        assert_eq!(
+            bottle.url_for_domain(&formula, "https://mirror.example.com/bottles/"),
+            "https://mirror.example.com/bottles/openssl%403-1.0.0_1.arm64_sonoma.bottle.tar.gz"
+        );
+        assert_eq!(
+            bottle.url_for_domain(&formula, "https://ghcr.io/v2/acme/core"),
+            "https://ghcr.io/v2/acme/core/openssl/3/blobs/sha256:abc123"
+        );
+        assert_eq!(
+            bottle.url_for_domain(&formula, DEFAULT_BOTTLE_DOMAIN),
+            bottle.url
         );
 
-        let formula = Formula {
-            name: "legacy".to_string(),
-            versions: Versions {
-                stable: "0.1.0".to_string(),
-            },
-            dependencies: Vec::new(),
-            bottle: Bottle {
-                stable: BottleStable { files, rebuild: 0 },
-            },
-            revision: 0,
+        formula.bottle.stable.rebuild = 2;
+        assert!(
+            bottle
+                .url_for_domain(&formula, "https://mirror.example.com")
+                .ends_with(".arm64_sonoma.bottle.2.tar.gz")
+        );
+
+        let tap_bottle = SelectedBottle {
+            url: "https://example.com/openssl@3.arm64_sonoma.bottle.tar.gz".to_string(),
+            ..bottle
         };
+        assert_eq!(
+            tap_bottle.url_for_domain(&formula, "https://mirror.example.com"),
+            tap_bottle.url
+        );
+    }
 
-        let err = select_bottle(&formula).unwrap_err();
+    #[test]
+    fn errors_when_no_bottle_matches_target() {
+        let formula = formula_with_tags("legacy", &["sonoma"]);
+        let err = select_bottle_for(&formula, &MACOS_ARM).unwrap_err();
         assert!(matches!(
             err,
             Error::UnsupportedBottle { name } if name == "legacy"
         ));
+
+        let formula = formula_with_tags("legacy", &["arm64_sonoma"]);
+        assert_eq!(selected_tag(&formula, MACOS_INTEL), None);
+        assert_eq!(selected_tag(&formula, LINUX_INTEL), None);
     }
 }
@@ -3,18 +3,50 @@ use std::path::PathBuf;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Error {
-    UnsupportedBottle { name: String },
-    ChecksumMismatch { expected: String, actual: String },
-    LinkConflict { path: PathBuf },
-    StoreCorruption { message: String },
-    NetworkFailure { message: String },
-    MissingFormula { name: String },
-    UnsupportedTap { name: String },
-    DependencyCycle { cycle: Vec<String> },
-    NotInstalled { name: String },
-    FileError { message: String },
-    InvalidArgument { message: String },
-    ExecutionError { message: String },
+    UnsupportedBottle {
+        name: String,
+    },
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+    LinkConflict {
+        path: PathBuf,
+    },
+    StoreCorruption {
+        message: String,
+    },
+    NetworkFailure {
+        message: String,
+    },
+    MissingFormula {
+        name: String,
+    },
+    UnsupportedTap {
+        name: String,
+    },
+    DependencyCycle {
+        cycle: Vec<String>,
+    },
+    NotInstalled {
+        name: String,
+    },
+    HasDependents {
+        name: String,
+        dependents: Vec<String>,
+    },
+    OfflineUnavailable {
+        missing: Vec<String>,
+    },
+    FileError {
+        message: String,
+    },
+    InvalidArgument {
+        message: String,
+    },
+    ExecutionError {
+        message: String,
+    },
 }
 
 impl fmt::Display for Error {
@@ -35,7 +67,7 @@ impl fmt::Display for Error {
             Error::UnsupportedTap { name } => {
                 write!(
                     f,
-                    "tap formula '{name}' is not supported (only homebrew/core)"
+                    "formula '{name}' comes from a tap that is not registered"
                 )
             }
             Error::DependencyCycle { cycle } => {
@@ -43,6 +75,17 @@ impl fmt::Display for Error {
                 write!(f, "dependency cycle detected: {rendered}")
             }
             Error::NotInstalled { name } => write!(f, "formula '{name}' is not installed"),
+            Error::HasDependents { name, dependents } => {
+                let rendered = dependents.join(", ");
+                write!(
+                    f,
+                    "formula '{name}' is required by installed formulas: {rendered}"
+                )
+            }
+            Error::OfflineUnavailable { missing } => {
+                let rendered = missing.join(", ");
+                write!(f, "offline mode: not available from cache: {rendered}")
+            }
             Error::FileError { message } => write!(f, "file error: {message}"),
             Error::InvalidArgument { message } => write!(f, "invalid argument: {message}"),
             Error::ExecutionError { message } => write!(f, "{message}"),
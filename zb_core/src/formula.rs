@@ -1,7 +1,7 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Formula {
     pub name: String,
     pub versions: Versions,
@@ -9,9 +9,21 @@ pub struct Formula {
     pub bottle: Bottle,
     #[serde(default)]
     pub revision: u32,
+    #[serde(default)]
+    pub desc: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Names the formula was previously published under
+    #[serde(default)]
+    pub oldnames: Vec<String>,
 }
 
 impl Formula {
+    /// Whether `name` is an alias or former name of this formula
+    pub fn is_known_as(&self, name: &str) -> bool {
+        self.aliases.iter().chain(&self.oldnames).any(|n| n == name)
+    }
+
     /// Returns the effective version including revision suffix if applicable.
     /// Homebrew formulas with revision > 0 have paths like `{version}_{revision}`.
     /// Note: `rebuild` (in bottle) does NOT affect the installation directory, only the bottle filename.
@@ -24,17 +36,30 @@ impl Formula {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+/// Split a tap-qualified name such as `acme/tools/deployctl` into its tap
+/// (`acme/tools`) and formula (`deployctl`). Unqualified names return `None`.
+pub fn split_tap_name(name: &str) -> Option<(&str, &str)> {
+    let (tap, formula) = name.rsplit_once('/')?;
+    (is_tap_name(tap) && !formula.is_empty()).then_some((tap, formula))
+}
+
+/// Whether `name` has the `user/repo` shape of a tap
+pub fn is_tap_name(name: &str) -> bool {
+    name.split_once('/')
+        .is_some_and(|(user, repo)| !user.is_empty() && !repo.is_empty() && !repo.contains('/'))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Versions {
     pub stable: String,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Bottle {
     pub stable: BottleStable,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BottleStable {
     pub files: BTreeMap<String, BottleFile>,
     /// Rebuild number for the bottle. When > 0, the bottle's internal paths
@@ -43,7 +68,7 @@ pub struct BottleStable {
     pub rebuild: u32,
 }
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BottleFile {
     pub url: String,
     pub sha256: String,
@@ -107,4 +132,16 @@ mod tests {
         let formula: Formula = serde_json::from_str(fixture).unwrap();
         assert_eq!(formula.revision, 0);
     }
+
+    #[test]
+    fn splits_tap_qualified_names() {
+        assert_eq!(
+            split_tap_name("acme/tools/deployctl"),
+            Some(("acme/tools", "deployctl"))
+        );
+        assert_eq!(split_tap_name("deployctl"), None);
+        assert_eq!(split_tap_name("tools/deployctl"), None);
+        assert_eq!(split_tap_name("a/b/c/deployctl"), None);
+        assert_eq!(split_tap_name("acme/tools/"), None);
+    }
 }
@@ -2,10 +2,19 @@ pub mod bottle;
 pub mod context;
 pub mod errors;
 pub mod formula;
+pub mod lockfile;
 pub mod resolve;
+pub mod search;
+pub mod version;
 
-pub use bottle::{SelectedBottle, select_bottle};
+pub use bottle::{
+    BottleSelector, BottleTarget, DEFAULT_BOTTLE_DOMAIN, SelectedBottle, TargetArch, TargetOs,
+    select_bottle, select_bottle_for,
+};
 pub use context::{ConcurrencyLimits, Context, LogLevel, LoggerHandle, Paths};
 pub use errors::Error;
-pub use formula::Formula;
-pub use resolve::resolve_closure;
+pub use formula::{Formula, is_tap_name, split_tap_name};
+pub use lockfile::{LockedFormula, Lockfile};
+pub use resolve::{DependencyGraph, dependency_graph, resolve_closure, topological_order};
+pub use search::search_formulas;
+pub use version::Version;
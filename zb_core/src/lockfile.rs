@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::formula::{Bottle, BottleFile, BottleStable, Versions};
+use crate::{Error, Formula, SelectedBottle};
+
+/// Bumped whenever the lockfile layout changes incompatibly
+pub const LOCKFILE_FORMAT: u32 = 1;
+
+/// A fully resolved install: every formula in topological order with the exact
+/// bottle that was selected for it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Lockfile {
+    pub format: u32,
+    /// The formulas that were asked for, sorted; used to detect a stale lockfile
+    pub roots: Vec<String>,
+    /// Roots that are aliases or old names, mapped to the formula they resolved to
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub renamed: BTreeMap<String, String>,
+    pub formulas: Vec<LockedFormula>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LockedFormula {
+    pub name: String,
+    /// Effective version, including the `_N` revision suffix when there is one
+    pub version: String,
+    #[serde(default)]
+    pub revision: u32,
+    #[serde(default)]
+    pub rebuild: u32,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    pub bottle: SelectedBottle,
+}
+
+impl Lockfile {
+    /// Build a lockfile from a resolved plan; `formulas` and `bottles` are paired by index
+    pub fn new(roots: &[String], formulas: &[Formula], bottles: &[SelectedBottle]) -> Self {
+        let mut roots = roots.to_vec();
+        roots.sort();
+        roots.dedup();
+
+        let formulas = formulas
+            .iter()
+            .zip(bottles)
+            .map(|(formula, bottle)| LockedFormula {
+                name: formula.name.clone(),
+                version: formula.effective_version(),
+                revision: formula.revision,
+                rebuild: formula.bottle.stable.rebuild,
+                dependencies: formula.dependencies.clone(),
+                bottle: bottle.clone(),
+            })
+            .collect();
+
+        Self {
+            format: LOCKFILE_FORMAT,
+            roots,
+            renamed: BTreeMap::new(),
+            formulas,
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, Error> {
+        let lockfile: Self = serde_json::from_str(contents).map_err(|e| Error::FileError {
+            message: format!("failed to parse lockfile: {e}"),
+        })?;
+
+        if lockfile.format != LOCKFILE_FORMAT {
+            return Err(Error::FileError {
+                message: format!(
+                    "unsupported lockfile format {} (expected {LOCKFILE_FORMAT})",
+                    lockfile.format
+                ),
+            });
+        }
+
+        Ok(lockfile)
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::FileError {
+            message: format!("failed to serialize lockfile: {e}"),
+        })
+    }
+
+    /// Whether the lockfile was resolved for exactly this set of root formulas
+    pub fn matches_roots(&self, roots: &[String]) -> bool {
+        let mut roots = roots.to_vec();
+        roots.sort();
+        roots.dedup();
+        roots == self.roots
+    }
+}
+
+impl LockedFormula {
+    /// Reconstruct a formula that carries only the locked bottle
+    pub fn to_formula(&self) -> Formula {
+        let stable = if self.revision > 0 {
+            self.version
+                .strip_suffix(&format!("_{}", self.revision))
+                .unwrap_or(&self.version)
+                .to_string()
+        } else {
+            self.version.clone()
+        };
+
+        let mut files = BTreeMap::new();
+        files.insert(
+            self.bottle.tag.clone(),
+            BottleFile {
+                url: self.bottle.url.clone(),
+                sha256: self.bottle.sha256.clone(),
+            },
+        );
+
+        Formula {
+            name: self.name.clone(),
+            versions: Versions { stable },
+            dependencies: self.dependencies.clone(),
+            bottle: Bottle {
+                stable: BottleStable {
+                    files,
+                    rebuild: self.rebuild,
+                },
+            },
+            revision: self.revision,
+            desc: None,
+            aliases: Vec::new(),
+            oldnames: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula_with_revision(revision: u32) -> Formula {
+        let mut formula: Formula =
+            serde_json::from_str(include_str!("../fixtures/formula_foo.json")).unwrap();
+        formula.revision = revision;
+        formula
+    }
+
+    fn bottle() -> SelectedBottle {
+        SelectedBottle {
+            tag: "arm64_sonoma".to_string(),
+            url: "https://example.com/foo.tar.gz".to_string(),
+            sha256: "deadbeef".repeat(8),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let formula = formula_with_revision(2);
+        let lockfile = Lockfile::new(
+            &["foo".to_string()],
+            std::slice::from_ref(&formula),
+            &[bottle()],
+        );
+        assert_eq!(lockfile.formulas[0].version, "1.2.3_2");
+
+        let parsed = Lockfile::parse(&lockfile.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, lockfile);
+
+        let restored = parsed.formulas[0].to_formula();
+        assert_eq!(restored.effective_version(), formula.effective_version());
+        assert_eq!(restored.versions.stable, "1.2.3");
+        assert_eq!(restored.bottle.stable.files.len(), 1);
+    }
+
+    #[test]
+    fn matches_roots_ignores_order_and_duplicates() {
+        let lockfile = Lockfile::new(&["wget".to_string(), "jq".to_string()], &[], &[]);
+        assert!(lockfile.matches_roots(&["jq".to_string(), "wget".to_string(), "jq".to_string()]));
+        assert!(!lockfile.matches_roots(&["jq".to_string()]));
+    }
+
+    #[test]
+    fn rejects_unknown_format() {
+        let err = Lockfile::parse(r#"{"format": 99, "roots": [], "formulas": []}"#).unwrap_err();
+        assert!(matches!(err, Error::FileError { message } if message.contains("unsupported")));
+    }
+}
@@ -4,12 +4,47 @@ use std::collections::{BTreeMap, BTreeSet};
 type InDegreeMap = BTreeMap<String, usize>;
 type AdjacencyMap = BTreeMap<String, BTreeSet<String>>;
 
+/// Each formula in a closure mapped to its sorted direct dependencies within that closure
+pub type DependencyGraph = BTreeMap<String, Vec<String>>;
+
 pub fn resolve_closure(
     roots: &[String],
     formulas: &BTreeMap<String, Formula>,
 ) -> Result<Vec<String>, Error> {
+    let graph = dependency_graph(roots, formulas)?;
+    topological_order(&graph)
+}
+
+/// Build the dependency graph for `roots` and everything they transitively depend on
+pub fn dependency_graph(
+    roots: &[String],
+    formulas: &BTreeMap<String, Formula>,
+) -> Result<DependencyGraph, Error> {
     let closure = compute_closure(roots, formulas)?;
-    let (mut indegree, adjacency) = build_graph(&closure, formulas)?;
+
+    closure
+        .iter()
+        .map(|name| {
+            let formula = formulas
+                .get(name)
+                .ok_or_else(|| Error::MissingFormula { name: name.clone() })?;
+            let mut deps: Vec<String> = formula
+                .dependencies
+                .iter()
+                .filter(|dep| closure.contains(*dep))
+                .cloned()
+                .collect();
+            deps.sort();
+            deps.dedup();
+            Ok((name.clone(), deps))
+        })
+        .collect()
+}
+
+/// Order a dependency graph so every formula comes after its dependencies.
+/// Ties are broken by name, and dependencies missing from the graph are ignored.
+pub fn topological_order(graph: &DependencyGraph) -> Result<Vec<String>, Error> {
+    let (mut indegree, adjacency) = build_graph(graph);
 
     let mut ready: BTreeSet<String> = indegree
         .iter()
@@ -22,7 +57,7 @@ pub fn resolve_closure(
         })
         .collect();
 
-    let mut ordered = Vec::with_capacity(closure.len());
+    let mut ordered = Vec::with_capacity(graph.len());
     while let Some(name) = ready.iter().next().cloned() {
         ready.take(&name);
         ordered.push(name.clone());
@@ -38,7 +73,7 @@ pub fn resolve_closure(
         }
     }
 
-    if ordered.len() != closure.len() {
+    if ordered.len() != graph.len() {
         let cycle: Vec<String> = indegree
             .into_iter()
             .filter_map(|(name, count)| if count > 0 { Some(name) } else { None })
@@ -82,31 +117,26 @@ fn compute_closure(
     Ok(closure)
 }
 
-fn build_graph(
-    closure: &BTreeSet<String>,
-    formulas: &BTreeMap<String, Formula>,
-) -> Result<(InDegreeMap, AdjacencyMap), Error> {
-    let mut indegree: InDegreeMap = closure.iter().map(|name| (name.clone(), 0)).collect();
+fn build_graph(graph: &DependencyGraph) -> (InDegreeMap, AdjacencyMap) {
+    let mut indegree: InDegreeMap = graph.keys().map(|name| (name.clone(), 0)).collect();
     let mut adjacency: AdjacencyMap = BTreeMap::new();
 
-    for name in closure {
-        let formula = formulas
-            .get(name)
-            .ok_or_else(|| Error::MissingFormula { name: name.clone() })?;
-        let mut deps = formula.dependencies.clone();
-        deps.sort();
+    for (name, deps) in graph {
         for dep in deps {
-            if !closure.contains(&dep) {
+            if !graph.contains_key(dep) {
                 continue;
             }
             if let Some(count) = indegree.get_mut(name) {
                 *count += 1;
             }
-            adjacency.entry(dep).or_default().insert(name.clone());
+            adjacency
+                .entry(dep.clone())
+                .or_default()
+                .insert(name.clone());
         }
     }
 
-    Ok((indegree, adjacency))
+    (indegree, adjacency)
 }
 
 #[cfg(test)]
@@ -135,6 +165,9 @@ mod tests {
                 stable: BottleStable { files, rebuild: 0 },
             },
             revision: 0,
+            desc: None,
+            aliases: Vec::new(),
+            oldnames: Vec::new(),
         }
     }
 
@@ -186,4 +219,28 @@ mod tests {
         // Should successfully resolve with just git and gettext
         assert_eq!(order, vec!["gettext", "git"]);
     }
+
+    #[test]
+    fn dependency_graph_limits_edges_to_closure() {
+        let mut formulas = BTreeMap::new();
+        formulas.insert("git".to_string(), formula("git", &["pcre2", "gettext"]));
+        formulas.insert("gettext".to_string(), formula("gettext", &["libiconv"]));
+        formulas.insert("pcre2".to_string(), formula("pcre2", &[]));
+        formulas.insert("unrelated".to_string(), formula("unrelated", &[]));
+
+        let graph = dependency_graph(&["git".to_string()], &formulas).unwrap();
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph["git"], vec!["gettext", "pcre2"]);
+        assert!(graph["gettext"].is_empty());
+    }
+
+    #[test]
+    fn topological_order_ignores_edges_leaving_the_graph() {
+        let mut graph = DependencyGraph::new();
+        graph.insert("curl".to_string(), vec!["openssl@3".to_string()]);
+        graph.insert("openssl@3".to_string(), vec!["ca-certificates".to_string()]);
+
+        let order = topological_order(&graph).unwrap();
+        assert_eq!(order, vec!["openssl@3", "curl"]);
+    }
 }
@@ -0,0 +1,208 @@
+use crate::Formula;
+
+/// How well a formula matched; lower ranks first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchKind {
+    ExactName,
+    ExactAlias,
+    NamePrefix,
+    NameSubstring,
+    AliasSubstring,
+    /// Name within a small edit distance of the query (typos)
+    NameTypo,
+    /// Query characters appear in order in the name (`rpgrp` -> `ripgrep`)
+    NameSubsequence,
+    Description,
+}
+
+/// Rank formulas against a search query.
+///
+/// Matching is case-insensitive. Name and alias matches rank above fuzzy name
+/// matches, which rank above description matches; ties go to the shorter name.
+/// With `desc_only`, only descriptions are searched.
+pub fn search_formulas<'a>(
+    formulas: impl IntoIterator<Item = &'a Formula>,
+    query: &str,
+    desc_only: bool,
+) -> Vec<&'a Formula> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut matches: Vec<(MatchKind, &Formula)> = formulas
+        .into_iter()
+        .filter_map(|formula| {
+            let kind = if desc_only {
+                description_matches(formula, &query).then_some(MatchKind::Description)
+            } else {
+                match_kind(formula, &query)
+            };
+            kind.map(|kind| (kind, formula))
+        })
+        .collect();
+
+    matches.sort_by(|(a_kind, a), (b_kind, b)| {
+        a_kind
+            .cmp(b_kind)
+            .then(a.name.len().cmp(&b.name.len()))
+            .then(a.name.cmp(&b.name))
+    });
+
+    matches.into_iter().map(|(_, formula)| formula).collect()
+}
+
+fn match_kind(formula: &Formula, query: &str) -> Option<MatchKind> {
+    let name = formula.name.to_lowercase();
+    let aliases: Vec<String> = formula.aliases.iter().map(|a| a.to_lowercase()).collect();
+
+    if name == query {
+        Some(MatchKind::ExactName)
+    } else if aliases.iter().any(|alias| alias == query) {
+        Some(MatchKind::ExactAlias)
+    } else if name.starts_with(query) {
+        Some(MatchKind::NamePrefix)
+    } else if name.contains(query) {
+        Some(MatchKind::NameSubstring)
+    } else if aliases.iter().any(|alias| alias.contains(query)) {
+        Some(MatchKind::AliasSubstring)
+    } else if is_typo_of(query, &name) {
+        Some(MatchKind::NameTypo)
+    } else if query.chars().count() >= 3 && is_subsequence(query, &name) {
+        Some(MatchKind::NameSubsequence)
+    } else if description_matches(formula, query) {
+        Some(MatchKind::Description)
+    } else {
+        None
+    }
+}
+
+/// Every whitespace-separated query word appears somewhere in the description
+fn description_matches(formula: &Formula, query: &str) -> bool {
+    let Some(desc) = &formula.desc else {
+        return false;
+    };
+    let desc = desc.to_lowercase();
+    query.split_whitespace().all(|word| desc.contains(word))
+}
+
+fn is_subsequence(query: &str, name: &str) -> bool {
+    let mut name = name.chars();
+    query.chars().all(|q| name.any(|c| c == q))
+}
+
+/// Short queries tolerate one edit, longer ones two
+fn is_typo_of(query: &str, name: &str) -> bool {
+    let query_len = query.chars().count();
+    if query_len < 4 {
+        return false;
+    }
+    let max_distance = if query_len >= 8 { 2 } else { 1 };
+    if query_len.abs_diff(name.chars().count()) > max_distance {
+        return false;
+    }
+    edit_distance(query, name) <= max_distance
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula(name: &str, aliases: &[&str], desc: &str) -> Formula {
+        let mut formula: Formula =
+            serde_json::from_str(include_str!("../fixtures/formula_foo.json")).unwrap();
+        formula.name = name.to_string();
+        formula.aliases = aliases.iter().map(|a| a.to_string()).collect();
+        formula.desc = Some(desc.to_string());
+        formula
+    }
+
+    fn names(results: Vec<&Formula>) -> Vec<&str> {
+        results.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    fn catalog() -> Vec<Formula> {
+        vec![
+            formula(
+                "ripgrep",
+                &["rg"],
+                "Search tool like grep and The Silver Searcher",
+            ),
+            formula(
+                "ripgrep-all",
+                &["rga"],
+                "Wrapper around ripgrep for PDFs and archives",
+            ),
+            formula("grep", &[], "GNU grep, egrep and fgrep"),
+            formula(
+                "jq",
+                &[],
+                "Lightweight and flexible command-line JSON processor",
+            ),
+            formula(
+                "python@3.12",
+                &["python3"],
+                "Interpreted, interactive, object-oriented language",
+            ),
+            formula("wget", &[], "Internet file retriever"),
+        ]
+    }
+
+    #[test]
+    fn ranks_name_matches_before_fuzzy_and_description_matches() {
+        let catalog = catalog();
+        assert_eq!(
+            names(search_formulas(&catalog, "grep", false)),
+            vec!["grep", "ripgrep", "ripgrep-all"]
+        );
+        assert_eq!(
+            names(search_formulas(&catalog, "RG", false)),
+            vec!["ripgrep", "ripgrep-all"]
+        );
+        assert_eq!(
+            names(search_formulas(&catalog, "python3", false)),
+            vec!["python@3.12"]
+        );
+    }
+
+    #[test]
+    fn tolerates_typos_and_abbreviations() {
+        let catalog = catalog();
+        assert_eq!(
+            names(search_formulas(&catalog, "wgett", false)),
+            vec!["wget"]
+        );
+        assert_eq!(
+            names(search_formulas(&catalog, "rpgrp", false)),
+            vec!["ripgrep", "ripgrep-all"]
+        );
+    }
+
+    #[test]
+    fn desc_only_searches_descriptions() {
+        let catalog = catalog();
+        assert_eq!(names(search_formulas(&catalog, "json", true)), vec!["jq"]);
+        assert_eq!(
+            names(search_formulas(&catalog, "ripgrep", true)),
+            vec!["ripgrep-all"]
+        );
+        assert!(search_formulas(&catalog, "wget", true).is_empty());
+        assert!(search_formulas(&catalog, "  ", false).is_empty());
+    }
+}
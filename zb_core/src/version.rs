@@ -0,0 +1,261 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A Homebrew package version such as `1.2.3`, `3.0.0-rc1`, `2024-01-01` or `1.2.3_1`.
+///
+/// Ordering follows Homebrew's rules: numeric segments compare numerically, missing
+/// trailing segments count as zero, pre-release tags (alpha < beta < pre < rc) sort
+/// before the release they precede, patch and post tags sort after it, and a trailing
+/// `_N` revision (as produced by `Formula::effective_version`) breaks ties.
+#[derive(Debug, Clone)]
+pub struct Version {
+    raw: String,
+    tokens: Vec<Token>,
+    revision: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Digits with leading zeros stripped, so arbitrarily long date stamps still compare
+    Numeric(String),
+    Tag(Tag, u64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Tag {
+    Alpha,
+    Beta,
+    Pre,
+    Rc,
+    Patch,
+    Post,
+}
+
+impl Tag {
+    fn is_prerelease(self) -> bool {
+        matches!(self, Tag::Alpha | Tag::Beta | Tag::Pre | Tag::Rc)
+    }
+
+    fn keyword(self) -> &'static str {
+        match self {
+            Tag::Alpha => "alpha",
+            Tag::Beta => "beta",
+            Tag::Pre => "pre",
+            Tag::Rc => "rc",
+            Tag::Patch => "p",
+            Tag::Post => "post",
+        }
+    }
+}
+
+impl Version {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        let (version, revision) = match raw.rsplit_once('_') {
+            Some((version, revision))
+                if !version.is_empty()
+                    && !revision.is_empty()
+                    && revision.bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                (version, revision.parse().unwrap_or(0))
+            }
+            _ => (raw, 0),
+        };
+
+        Self {
+            raw: raw.to_string(),
+            tokens: tokenize(version),
+            revision,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+fn tokenize(version: &str) -> Vec<Token> {
+    let chars: Vec<char> = version.to_ascii_lowercase().chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        if chars[i].is_ascii_digit() {
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            tokens.push(numeric(&chars[start..i]));
+        } else if chars[i].is_ascii_alphabetic() {
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+
+            let digits_start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let digits = &chars[digits_start..i];
+            let number = || digits.iter().collect::<String>().parse().unwrap_or(0);
+            let preceded_by_dot = start > 0 && chars[start - 1] == '.';
+
+            let tag = match word.as_str() {
+                "alpha" => Some(Tag::Alpha),
+                "a" if !digits.is_empty() => Some(Tag::Alpha),
+                "beta" => Some(Tag::Beta),
+                "b" if !digits.is_empty() => Some(Tag::Beta),
+                "pre" => Some(Tag::Pre),
+                "rc" => Some(Tag::Rc),
+                "p" | "pl" | "patch" => Some(Tag::Patch),
+                "post" if preceded_by_dot && !digits.is_empty() => Some(Tag::Post),
+                _ => None,
+            };
+
+            match tag {
+                Some(tag) => tokens.push(Token::Tag(tag, number())),
+                None => {
+                    tokens.push(Token::Text(word));
+                    if !digits.is_empty() {
+                        tokens.push(numeric(digits));
+                    }
+                }
+            }
+        } else {
+            // Separators such as '.', '-' and '+' only delimit tokens
+            i += 1;
+        }
+    }
+
+    tokens
+}
+
+fn numeric(digits: &[char]) -> Token {
+    let value: String = digits.iter().skip_while(|c| **c == '0').collect();
+    Token::Numeric(value)
+}
+
+/// How a missing token (the shorter version running out) compares to `token`
+fn cmp_missing(token: &Token) -> Ordering {
+    match token {
+        Token::Numeric(value) if value.is_empty() => Ordering::Equal,
+        Token::Tag(tag, _) if tag.is_prerelease() => Ordering::Greater,
+        _ => Ordering::Less,
+    }
+}
+
+fn cmp_tokens(a: Option<&Token>, b: Option<&Token>) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(b)) => cmp_missing(b),
+        (Some(a), None) => cmp_missing(a).reverse(),
+        (Some(Token::Numeric(a)), Some(Token::Numeric(b))) => {
+            a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+        }
+        (Some(Token::Numeric(_)), Some(_)) => Ordering::Greater,
+        (Some(_), Some(Token::Numeric(_))) => Ordering::Less,
+        (Some(Token::Tag(a, an)), Some(Token::Tag(b, bn))) => a.cmp(b).then(an.cmp(bn)),
+        (Some(Token::Tag(tag, _)), Some(Token::Text(text))) => tag.keyword().cmp(text.as_str()),
+        (Some(Token::Text(text)), Some(Token::Tag(tag, _))) => text.as_str().cmp(tag.keyword()),
+        (Some(Token::Text(a)), Some(Token::Text(b))) => a.cmp(b),
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.tokens.len().max(other.tokens.len());
+        for i in 0..len {
+            let ordering = cmp_tokens(self.tokens.get(i), other.tokens.get(i));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        self.revision.cmp(&other.revision)
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(raw: &str) -> Version {
+        Version::parse(raw)
+    }
+
+    #[test]
+    fn numeric_segments_compare_numerically() {
+        assert!(v("1.2.10") > v("1.2.9"));
+        assert!(v("1.10") > v("1.9.9"));
+        assert!(v("2.0") > v("1.99"));
+        assert_eq!(v("1.0"), v("1.0.0"));
+        assert!(v("1.0.1") > v("1.0"));
+    }
+
+    #[test]
+    fn prerelease_tags_sort_before_release() {
+        assert!(v("1.0alpha1") < v("1.0beta1"));
+        assert!(v("1.0beta2") < v("1.0pre1"));
+        assert!(v("1.0pre1") < v("1.0rc1"));
+        assert!(v("1.0-rc1") < v("1.0-rc2"));
+        assert!(v("1.0rc2") < v("1.0"));
+        assert!(v("3.0.0-beta") < v("3.0.0"));
+        assert!(v("1.0b3") < v("1.0"));
+    }
+
+    #[test]
+    fn patch_and_post_tags_sort_after_release() {
+        assert!(v("9.9p1") > v("9.9"));
+        assert!(v("9.9p2") > v("9.9p1"));
+        assert!(v("1.0.post1") > v("1.0"));
+    }
+
+    #[test]
+    fn trailing_letters_sort_after_release() {
+        assert!(v("1.1.1w") > v("1.1.1v"));
+        assert!(v("1.1.1a") > v("1.1.1"));
+        assert!(v("1.1.2") > v("1.1.1w"));
+    }
+
+    #[test]
+    fn revision_suffix_breaks_ties() {
+        assert!(v("1.2.3_1") > v("1.2.3"));
+        assert!(v("1.2.3_2") > v("1.2.3_1"));
+        assert!(v("1.2.4") > v("1.2.3_5"));
+        assert_eq!(v("1.2.3_0"), v("1.2.3"));
+    }
+
+    #[test]
+    fn date_style_versions() {
+        assert!(v("2024-02-01") > v("2024-01-31"));
+        assert!(v("20240201") > v("20240131"));
+        assert!(v("2025.01.15") > v("2024.12.31"));
+        assert!(v("20250101123456789012345") > v("20250101123456789012344"));
+    }
+
+    #[test]
+    fn display_preserves_original() {
+        assert_eq!(v("1.2.3_1").to_string(), "1.2.3_1");
+        assert_eq!(v("2024-01-01").as_str(), "2024-01-01");
+    }
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+use zb_core::Error;
+
+use crate::cellar::materialize::copy_dir_recursive;
+use crate::extraction::patch::restore_placeholders;
+
+/// Package a keg as a Homebrew-layout bottle at `output`, with `{name}/{version}/`
+/// at its root and `prefix` and `cellar` turned back into placeholders so any
+/// prefix can install it. The keg itself is left untouched. Returns the sha256.
+pub fn write_bottle(
+    keg_path: &Path,
+    name: &str,
+    version: &str,
+    prefix: &Path,
+    cellar: &Path,
+    output: &Path,
+) -> Result<String, Error> {
+    let staging = with_suffix(output, ".staging");
+    let _ = fs::remove_dir_all(&staging);
+
+    let result = (|| {
+        let staged_keg = staging.join(name).join(version);
+        copy_dir_recursive(keg_path, &staged_keg, false)?;
+        restore_placeholders(&staged_keg, prefix, cellar)?;
+        write_tarball(&staging.join(name), name, output)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result?;
+
+    let mut file = fs::File::open(output).map_err(|e| file_error(output, e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| file_error(output, e))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn write_tarball(source: &Path, name: &str, output: &Path) -> Result<(), Error> {
+    let part = with_suffix(output, ".part");
+    let result = (|| -> std::io::Result<()> {
+        let encoder = GzEncoder::new(fs::File::create(&part)?, Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        // Bottles ship their symlinks as symlinks
+        builder.follow_symlinks(false);
+        builder.append_dir_all(name, source)?;
+        builder.into_inner()?.finish()?;
+        fs::rename(&part, output)
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&part);
+        return Err(file_error(output, e));
+    }
+    Ok(())
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn file_error(path: &Path, e: impl std::fmt::Display) -> Error {
+    Error::FileError {
+        message: format!("failed to write bottle {}: {e}", path.display()),
+    }
+}
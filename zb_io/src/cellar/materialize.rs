@@ -38,6 +38,22 @@ impl Cellar {
         self.keg_path(name, version).exists()
     }
 
+    /// The prefix and cellar paths `materialize` substitutes for the
+    /// `@@HOMEBREW_PREFIX@@` and `@@HOMEBREW_CELLAR@@` placeholders
+    pub fn placeholder_paths(&self) -> (PathBuf, PathBuf) {
+        let prefix = self
+            .cellar_dir
+            .parent()
+            .unwrap_or(&self.cellar_dir)
+            .to_path_buf();
+        // The Linux patcher derives the cellar from the prefix rather than using ours
+        #[cfg(target_os = "linux")]
+        let cellar = prefix.join("Cellar");
+        #[cfg(not(target_os = "linux"))]
+        let cellar = self.cellar_dir.clone();
+        (prefix, cellar)
+    }
+
     pub fn materialize(
         &self,
         name: &str,
@@ -180,7 +196,7 @@ fn try_clonefile_dir(src: &Path, dst: &Path) -> io::Result<()> {
     }
 }
 
-fn copy_dir_recursive(src: &Path, dst: &Path, try_hardlink: bool) -> Result<(), Error> {
+pub(crate) fn copy_dir_recursive(src: &Path, dst: &Path, try_hardlink: bool) -> Result<(), Error> {
     fs::create_dir_all(dst).map_err(|e| Error::StoreCorruption {
         message: format!("failed to create directory {}: {e}", dst.display()),
     })?;
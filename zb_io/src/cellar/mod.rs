@@ -1,3 +1,4 @@
+pub mod bottle;
 pub mod link;
 pub mod materialize;
 
@@ -50,6 +50,11 @@ fn detect_compression(path: &Path) -> Result<CompressionFormat, Error> {
 }
 
 pub fn extract_tarball(tarball_path: &Path, dest_dir: &Path) -> Result<(), Error> {
+    extract_tar_archive(open_tarball(tarball_path)?, dest_dir)
+}
+
+/// Open a gzip, xz or zstd compressed tarball as a stream of tar data
+pub fn open_tarball(tarball_path: &Path) -> Result<Box<dyn Read>, Error> {
     let format = detect_compression(tarball_path)?;
 
     let file = File::open(tarball_path).map_err(|e| Error::StoreCorruption {
@@ -57,27 +62,19 @@ pub fn extract_tarball(tarball_path: &Path, dest_dir: &Path) -> Result<(), Error
     })?;
     let reader = BufReader::new(file);
 
-    match format {
-        CompressionFormat::Gzip => {
-            let decoder = GzDecoder::new(reader);
-            extract_tar_archive(decoder, dest_dir)
-        }
-        CompressionFormat::Xz => {
-            let decoder = XzDecoder::new(reader);
-            extract_tar_archive(decoder, dest_dir)
-        }
+    Ok(match format {
+        CompressionFormat::Gzip => Box::new(GzDecoder::new(reader)),
+        CompressionFormat::Xz => Box::new(XzDecoder::new(reader)),
         CompressionFormat::Zstd => {
-            let decoder = ZstdDecoder::new(reader).map_err(|e| Error::StoreCorruption {
-                message: format!("failed to create zstd decoder: {e}"),
-            })?;
-            extract_tar_archive(decoder, dest_dir)
-        }
-        CompressionFormat::Unknown => {
-            // Try gzip as fallback
-            let decoder = GzDecoder::new(reader);
-            extract_tar_archive(decoder, dest_dir)
+            Box::new(
+                ZstdDecoder::new(reader).map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to create zstd decoder: {e}"),
+                })?,
+            )
         }
-    }
+        // Try gzip as fallback
+        CompressionFormat::Unknown => Box::new(GzDecoder::new(reader)),
+    })
 }
 
 fn extract_tar_archive<R: Read>(reader: R, dest_dir: &Path) -> Result<(), Error> {
@@ -1,4 +1,4 @@
 pub mod extract;
 pub mod patch;
 
-pub use extract::{extract_tarball, extract_tarball_from_reader};
+pub use extract::{extract_tarball, extract_tarball_from_reader, open_tarball};
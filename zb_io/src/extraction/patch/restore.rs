@@ -0,0 +1,297 @@
+//! The reverse of placeholder patching: turn a materialized keg back into a
+//! relocatable one so it can be bottled again.
+
+use std::fs;
+use std::io::Read;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use zb_core::Error;
+
+const PREFIX_PLACEHOLDER: &str = "@@HOMEBREW_PREFIX@@";
+const CELLAR_PLACEHOLDER: &str = "@@HOMEBREW_CELLAR@@";
+
+/// Maps the concrete prefix and cellar back to their placeholders
+struct Relocation {
+    prefix: String,
+    cellar: String,
+}
+
+impl Relocation {
+    /// The cellar usually sits inside the prefix, so it is replaced first
+    fn restore(&self, value: &str) -> Option<String> {
+        if !value.contains(&self.prefix) && !value.contains(&self.cellar) {
+            return None;
+        }
+        Some(
+            value
+                .replace(&self.cellar, CELLAR_PLACEHOLDER)
+                .replace(&self.prefix, PREFIX_PLACEHOLDER),
+        )
+    }
+}
+
+/// Put `@@HOMEBREW_CELLAR@@` and `@@HOMEBREW_PREFIX@@` back in place of `cellar` and
+/// `prefix` throughout a copy of a keg: in text files, in ELF RPATHs and interpreters
+/// on Linux and in Mach-O install names on macOS. Works in place, so never point
+/// this at an installed keg.
+pub fn restore_placeholders(keg_path: &Path, prefix: &Path, cellar: &Path) -> Result<(), Error> {
+    let relocation = Relocation {
+        prefix: prefix.to_string_lossy().to_string(),
+        cellar: cellar.to_string_lossy().to_string(),
+    };
+
+    let files: Vec<PathBuf> = walkdir::WalkDir::new(keg_path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().to_path_buf())
+        .collect();
+
+    for path in &files {
+        let mut head = [0u8; 8192];
+        let n = fs::File::open(path)
+            .and_then(|mut file| file.read(&mut head))
+            .map_err(|e| file_error(path, e))?;
+        let head = &head[..n];
+
+        if head.starts_with(b"\x7fELF") {
+            #[cfg(target_os = "linux")]
+            restore_elf(path, &relocation)?;
+        } else if is_macho(head) {
+            #[cfg(target_os = "macos")]
+            restore_macho(path, &relocation)?;
+        } else if !head.contains(&0) {
+            restore_text(path, &relocation)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn file_error(path: &Path, e: impl std::fmt::Display) -> Error {
+    Error::FileError {
+        message: format!("failed to restore placeholders in {}: {e}", path.display()),
+    }
+}
+
+fn is_macho(head: &[u8]) -> bool {
+    head.len() >= 4
+        && matches!(
+            u32::from_be_bytes([head[0], head[1], head[2], head[3]]),
+            0xfeedface | 0xfeedfacf | 0xcafebabe | 0xcefaedfe | 0xcffaedfe
+        )
+}
+
+fn restore_text(path: &Path, relocation: &Relocation) -> Result<(), Error> {
+    // Not valid UTF-8 means not a text file we can safely rewrite
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(());
+    };
+    match relocation.restore(&content) {
+        Some(restored) => write_preserving_mode(path, restored.as_bytes()),
+        None => Ok(()),
+    }
+}
+
+/// Write through a read-only file and put its mode back afterwards
+fn write_preserving_mode(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let permissions = fs::metadata(path)
+        .map_err(|e| file_error(path, e))?
+        .permissions();
+    let original_mode = permissions.mode();
+
+    if original_mode & 0o200 == 0 {
+        let mut writable = permissions.clone();
+        writable.set_mode(original_mode | 0o200);
+        fs::set_permissions(path, writable).map_err(|e| file_error(path, e))?;
+    }
+
+    fs::write(path, data).map_err(|e| file_error(path, e))?;
+    fs::set_permissions(path, permissions).map_err(|e| file_error(path, e))
+}
+
+/// Restore RPATH entries and the interpreter. Interpreters zerobrew pointed at
+/// the system `ld.so` are left alone; installs pick one again anyway.
+#[cfg(target_os = "linux")]
+fn restore_elf(path: &Path, relocation: &Relocation) -> Result<(), Error> {
+    let content = fs::read(path).map_err(|e| file_error(path, e))?;
+    // Static archives and objects that arwen can't parse have nothing to restore
+    let Ok(mut elf) = arwen::elf::ElfContainer::parse(&content) else {
+        return Ok(());
+    };
+
+    let page_size = elf.get_page_size();
+    let _ = elf.set_page_size(page_size);
+
+    let mut changed = false;
+
+    let rpaths = elf.get_rpath();
+    let restored: Vec<String> = rpaths
+        .iter()
+        .map(|rpath| relocation.restore(rpath).unwrap_or_else(|| rpath.clone()))
+        .collect();
+    if restored != rpaths {
+        elf.set_runpath(restored.join(":"))
+            .map_err(|e| file_error(path, e))?;
+        changed = true;
+    }
+
+    let interpreter = elf
+        .inner
+        .elf_interpreter()
+        .map(|bytes| String::from_utf8_lossy(bytes).to_string());
+    if let Some(restored) = interpreter.and_then(|interp| relocation.restore(&interp)) {
+        elf.set_interpreter(&restored)
+            .map_err(|e| file_error(path, e))?;
+        changed = true;
+    }
+
+    if !changed {
+        return Ok(());
+    }
+
+    let mut patched = Vec::new();
+    elf.write(&mut patched).map_err(|e| file_error(path, e))?;
+    write_preserving_mode(path, &patched)
+}
+
+/// Restore linked library paths and the install name, then re-sign
+#[cfg(target_os = "macos")]
+fn restore_macho(path: &Path, relocation: &Relocation) -> Result<(), Error> {
+    use std::process::Command;
+
+    let path_str = path.to_string_lossy().to_string();
+    let otool = |flag: &str| -> Vec<String> {
+        Command::new("otool")
+            .args([flag, &path_str])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    // The first line names the file itself
+                    .skip(1)
+                    .filter_map(|line| line.split_whitespace().next())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut args: Vec<String> = Vec::new();
+    for id in otool("-D") {
+        if let Some(restored) = relocation.restore(&id) {
+            args.extend(["-id".to_string(), restored]);
+        }
+    }
+    for library in otool("-L") {
+        if let Some(restored) = relocation.restore(&library) {
+            args.extend(["-change".to_string(), library, restored]);
+        }
+    }
+    if args.is_empty() {
+        return Ok(());
+    }
+
+    let permissions = fs::metadata(path)
+        .map_err(|e| file_error(path, e))?
+        .permissions();
+    let mut writable = permissions.clone();
+    writable.set_mode(permissions.mode() | 0o200);
+    fs::set_permissions(path, writable).map_err(|e| file_error(path, e))?;
+
+    let status = Command::new("install_name_tool")
+        .args(&args)
+        .arg(&path_str)
+        .status()
+        .map_err(|e| file_error(path, e))?;
+    if !status.success() {
+        return Err(file_error(path, "install_name_tool failed"));
+    }
+    let _ = Command::new("codesign")
+        .args(["--force", "--sign", "-", &path_str])
+        .output();
+
+    fs::set_permissions(path, permissions).map_err(|e| file_error(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn restores_placeholders_in_text_files() {
+        let tmp = TempDir::new().unwrap();
+        let keg = tmp.path().join("keg");
+        fs::create_dir_all(keg.join("bin")).unwrap();
+
+        let script = keg.join("bin/tool");
+        fs::write(
+            &script,
+            "#!/bin/sh\nexec /opt/zb/cellar/tool/1.0/libexec/tool --lib /opt/zb/lib\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o555)).unwrap();
+
+        restore_placeholders(&keg, Path::new("/opt/zb"), Path::new("/opt/zb/cellar")).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&script).unwrap(),
+            "#!/bin/sh\nexec @@HOMEBREW_CELLAR@@/tool/1.0/libexec/tool --lib @@HOMEBREW_PREFIX@@/lib\n"
+        );
+        assert_eq!(
+            fs::metadata(&script).unwrap().permissions().mode() & 0o777,
+            0o555
+        );
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn restores_elf_rpaths() {
+        use crate::extraction::patch::linux::patch_placeholders;
+        use std::process::Command;
+
+        let tmp = TempDir::new().unwrap();
+        let prefix = tmp.path().join("prefix");
+        let keg = prefix.join("Cellar/tool/1.0");
+        fs::create_dir_all(keg.join("bin")).unwrap();
+
+        let src = tmp.path().join("tool.c");
+        fs::write(&src, "int main() { return 0; }").unwrap();
+        let binary = keg.join("bin/tool");
+        let compiled = Command::new("cc")
+            .arg(&src)
+            .arg("-o")
+            .arg(&binary)
+            .arg("-Wl,-rpath,@@HOMEBREW_PREFIX@@/opt/tool/lib")
+            .status()
+            .is_ok_and(|status| status.success());
+        if !compiled {
+            eprintln!("Skipping ELF restore test: cc not found");
+            return;
+        }
+
+        patch_placeholders(&keg, &prefix, "tool", "1.0").unwrap();
+        let rpaths = |path: &Path| {
+            let content = fs::read(path).unwrap();
+            arwen::elf::ElfContainer::parse(&content)
+                .unwrap()
+                .get_rpath()
+        };
+        assert!(rpaths(&binary).iter().all(|r| !r.contains("@@")));
+
+        restore_placeholders(&keg, &prefix, &prefix.join("Cellar")).unwrap();
+        assert_eq!(
+            rpaths(&binary),
+            vec![
+                "@@HOMEBREW_PREFIX@@/opt/tool/lib",
+                "@@HOMEBREW_PREFIX@@/lib"
+            ]
+        );
+    }
+}
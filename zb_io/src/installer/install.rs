@@ -1,19 +1,31 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 use std::sync::Arc;
 
+use crate::cellar::bottle::write_bottle;
 use crate::cellar::link::{LinkedFile, Linker};
 use crate::cellar::materialize::Cellar;
-use crate::network::api::ApiClient;
+use crate::network::api::{ApiClient, domain_from_env, is_url};
+use crate::network::cache::ApiCache;
 use crate::network::download::{
     DownloadProgressCallback, DownloadRequest, DownloadResult, ParallelDownloader,
 };
+use crate::network::mirror::MirrorServer;
 use crate::progress::{InstallProgress, ProgressCallback};
+use crate::storage::archive::{
+    ArchivedBottle, CACHE_ARCHIVE_FORMAT, CacheArchive, CacheManifest, import_cache_archive,
+    write_cache_archive,
+};
 use crate::storage::blob::BlobCache;
-use crate::storage::db::Database;
+use crate::storage::db::{Database, InstalledKeg};
 use crate::storage::store::Store;
 
-use zb_core::{Error, Formula, SelectedBottle, resolve_closure, select_bottle};
+use zb_core::formula::{Bottle, BottleFile, BottleStable, Versions};
+use zb_core::{
+    BottleSelector, BottleTarget, DependencyGraph, Error, Formula, Lockfile, SelectedBottle,
+    TargetOs, Version, dependency_graph, is_tap_name, resolve_closure, search_formulas,
+    select_bottle, split_tap_name,
+};
 
 /// Maximum number of retries for corrupted downloads
 const MAX_CORRUPTION_RETRIES: usize = 3;
@@ -25,17 +37,92 @@ pub struct Installer {
     cellar: Cellar,
     linker: Linker,
     db: Database,
+    /// Resolve and install from cached data only
+    offline: bool,
+    /// Fetch Homebrew's bottles from here instead of ghcr.io
+    bottle_domain: Option<String>,
 }
 
 pub struct InstallPlan {
     pub formulas: Vec<Formula>,
     pub bottles: Vec<SelectedBottle>,
+    /// Formulas the user asked for; everything else in the plan is a dependency
+    pub requested: BTreeSet<String>,
+    /// Formulas to install without linking, even when linking is requested
+    pub skip_link: BTreeSet<String>,
+    /// Requested names that resolved to a differently named formula, such as an
+    /// alias (`python` -> `python@3.13`) or an old name
+    pub renamed: BTreeMap<String, String>,
+    /// The third-party tap each formula fetched as `user/repo/name` came from
+    pub taps: BTreeMap<String, String>,
+}
+
+impl InstallPlan {
+    /// Rebuild a plan from a lockfile without consulting the formula API.
+    /// Fails if a locked bottle can't be installed on this platform.
+    pub fn from_lockfile(lockfile: &Lockfile) -> Result<Self, Error> {
+        let formulas: Vec<Formula> = lockfile
+            .formulas
+            .iter()
+            .map(|locked| locked.to_formula())
+            .collect();
+
+        let mut bottles = Vec::with_capacity(formulas.len());
+        for formula in &formulas {
+            bottles.push(select_bottle(formula)?);
+        }
+
+        Ok(Self {
+            formulas,
+            bottles,
+            requested: lockfile
+                .roots
+                .iter()
+                .map(|root| lockfile.renamed.get(root).unwrap_or(root).clone())
+                .collect(),
+            skip_link: BTreeSet::new(),
+            renamed: lockfile.renamed.clone(),
+            taps: taps_from_renamed(&lockfile.renamed),
+        })
+    }
+
+    /// The formula name a requested name resolved to
+    pub fn canonical_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.renamed.get(name).map_or(name, String::as_str)
+    }
 }
 
 pub struct ExecuteResult {
     pub installed: usize,
 }
 
+/// An installed keg that is older than what the formula API currently serves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutdatedPackage {
+    pub name: String,
+    pub installed_version: String,
+    pub current_version: String,
+}
+
+/// A formula from the formula index that matched a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    pub name: String,
+    pub version: String,
+    pub desc: Option<String>,
+    pub installed: bool,
+}
+
+/// A bottle written by `Installer::bottle`, with the formula JSON describing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuiltBottle {
+    pub path: std::path::PathBuf,
+    pub json_path: std::path::PathBuf,
+    pub formula: Formula,
+    pub tag: String,
+    pub sha256: String,
+}
+
 /// Internal struct for tracking processed packages during streaming install
 #[derive(Clone)]
 struct ProcessedPackage {
@@ -43,17 +130,29 @@ struct ProcessedPackage {
     version: String,
     store_key: String,
     linked_files: Vec<LinkedFile>,
+    /// Version of a previously installed keg that this package replaces
+    replaced_version: Option<String>,
+    /// Direct dependencies resolved for this package
+    dependencies: Vec<String>,
+    /// Aliases and old names the keg can also be looked up by
+    aliases: Vec<String>,
+    /// Third-party tap the formula came from
+    tap: Option<String>,
 }
 
 impl Installer {
     pub fn new(
-        api_client: ApiClient,
+        mut api_client: ApiClient,
         blob_cache: BlobCache,
         store: Store,
         cellar: Cellar,
         linker: Linker,
         db: Database,
     ) -> Self {
+        if let Ok(taps) = db.list_taps() {
+            api_client.set_taps(taps);
+        }
+
         Self {
             api_client,
             downloader: ParallelDownloader::new(blob_cache),
@@ -61,16 +160,110 @@ impl Installer {
             cellar,
             linker,
             db,
+            offline: false,
+            bottle_domain: None,
+        }
+    }
+
+    /// Resolve formulas from the bulk formula index rather than one request each
+    pub fn set_formula_index(&mut self, enabled: bool) {
+        self.api_client.set_formula_index(enabled);
+    }
+
+    /// Serve formula metadata and bottles from the caches only, never the network
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+        self.api_client.set_offline(offline);
+        self.downloader.set_offline(offline);
+    }
+
+    /// Serve Homebrew's bottles from `domain` as `HOMEBREW_BOTTLE_DOMAIN` does
+    pub fn set_bottle_domain(&mut self, domain: Option<String>) {
+        self.bottle_domain = domain;
+    }
+
+    /// Where to download `bottle` from, honoring the bottle domain
+    fn download_request(&self, formula: &Formula, bottle: &SelectedBottle) -> DownloadRequest {
+        let url = match &self.bottle_domain {
+            Some(domain) => bottle.url_for_domain(formula, domain),
+            None => bottle.url.clone(),
+        };
+        DownloadRequest {
+            url,
+            sha256: bottle.sha256.clone(),
+            name: formula.name.clone(),
+        }
+    }
+
+    /// Try a shared HTTP cache before the origin and upload verified bottles to it
+    pub fn set_remote_cache(&mut self, url: Option<String>) {
+        self.downloader.set_remote_cache(url);
+    }
+
+    /// Wait for bottles still being uploaded to the remote cache. Must run before
+    /// the process exits, or those uploads are cancelled with the runtime.
+    pub async fn finish_uploads(&self) {
+        self.downloader.finish_uploads().await;
+    }
+
+    /// A mirror serving this machine's cached bottles, plus the formula JSON in
+    /// `api_cache` when given. The cache must hold responses from this installer's API.
+    pub fn mirror_server(&self, api_cache: Option<ApiCache>) -> MirrorServer {
+        let server = MirrorServer::new(self.downloader.blob_cache().clone());
+        match api_cache {
+            Some(cache) => server.with_formulas(cache, self.api_client.base_url()),
+            None => server,
         }
     }
 
     /// Resolve dependencies and plan the install
     pub async fn plan(&self, names: &[String]) -> Result<InstallPlan, Error> {
+        self.plan_for(names, &BottleSelector::host()).await
+    }
+
+    /// Resolve dependencies and pick bottles with `selector`, which may describe a
+    /// platform other than this one. Such plans are only good for fetching.
+    pub async fn plan_for(
+        &self,
+        names: &[String],
+        selector: &BottleSelector,
+    ) -> Result<InstallPlan, Error> {
+        self.resolve_plan(names, Vec::new(), selector).await
+    }
+
+    /// Plan an install of formulas that don't come from the API, such as a formula
+    /// JSON file or a local bottle, alongside `names`. Their dependencies are still
+    /// resolved through the API.
+    pub async fn plan_with_formulas(
+        &self,
+        names: &[String],
+        provided: Vec<Formula>,
+    ) -> Result<InstallPlan, Error> {
+        self.resolve_plan(names, provided, &BottleSelector::host())
+            .await
+    }
+
+    async fn resolve_plan(
+        &self,
+        names: &[String],
+        provided: Vec<Formula>,
+        selector: &BottleSelector,
+    ) -> Result<InstallPlan, Error> {
+        let provided_names: Vec<String> = provided.iter().map(|f| f.name.clone()).collect();
+
         // Recursively fetch all formulas we need
-        let formulas = self.fetch_all_formulas(names).await?;
+        let (formulas, mut renamed) = self.fetch_all_formulas(names, provided, selector).await?;
+        // Dependencies fetched from taps are recorded too, so look before trimming
+        let taps = taps_from_renamed(&renamed);
+        renamed.retain(|name, _| names.contains(name));
+        let roots: Vec<String> = names
+            .iter()
+            .map(|name| renamed.get(name).unwrap_or(name).clone())
+            .chain(provided_names)
+            .collect();
 
         // Resolve in topological order
-        let ordered = resolve_closure(names, &formulas)?;
+        let ordered = resolve_closure(&roots, &formulas)?;
 
         // Build list of formulas in order
         let all_formulas: Vec<Formula> = ordered
@@ -81,16 +274,69 @@ impl Installer {
         // Select bottles for each formula
         let mut bottles = Vec::new();
         for formula in &all_formulas {
-            let bottle = select_bottle(formula)?;
+            let bottle = selector.select(formula)?;
             bottles.push(bottle);
         }
 
         Ok(InstallPlan {
             formulas: all_formulas,
             bottles,
+            requested: roots.into_iter().collect(),
+            skip_link: BTreeSet::new(),
+            renamed,
+            taps,
         })
     }
 
+    /// Fetch formulas from the API and build the dependency graph an install would resolve
+    pub async fn dependency_graph(&self, names: &[String]) -> Result<DependencyGraph, Error> {
+        let (formulas, renamed) = self
+            .fetch_all_formulas(names, Vec::new(), &BottleSelector::host())
+            .await?;
+        let roots: Vec<String> = names
+            .iter()
+            .map(|name| renamed.get(name).unwrap_or(name).clone())
+            .collect();
+        dependency_graph(&roots, &formulas)
+    }
+
+    /// Build the dependency graph of installed kegs from the edges recorded at install time.
+    /// An empty `names` slice starts from every installed keg.
+    pub fn installed_dependency_graph(&self, names: &[String]) -> Result<DependencyGraph, Error> {
+        let mut to_visit: Vec<String> = if names.is_empty() {
+            self.db
+                .list_installed()?
+                .into_iter()
+                .map(|keg| keg.name)
+                .collect()
+        } else {
+            for name in names {
+                if self.db.get_installed(name).is_none() {
+                    return Err(Error::NotInstalled { name: name.clone() });
+                }
+            }
+            names.to_vec()
+        };
+
+        let mut graph = DependencyGraph::new();
+        while let Some(name) = to_visit.pop() {
+            if graph.contains_key(&name) {
+                continue;
+            }
+            // Edges can outlive a dependency removed with --ignore-dependencies
+            let deps: Vec<String> = self
+                .db
+                .get_dependencies(&name)?
+                .into_iter()
+                .filter(|dep| self.db.get_installed(dep).is_some())
+                .collect();
+            to_visit.extend(deps.iter().cloned());
+            graph.insert(name, deps);
+        }
+
+        Ok(graph)
+    }
+
     /// Try to extract a download, with automatic retry on corruption
     async fn extract_with_retry(
         &self,
@@ -119,11 +365,7 @@ impl Installer {
                         );
 
                         // Re-download
-                        let request = DownloadRequest {
-                            url: bottle.url.clone(),
-                            sha256: bottle.sha256.clone(),
-                            name: formula.name.clone(),
-                        };
+                        let request = self.download_request(formula, bottle);
 
                         match self
                             .downloader
@@ -159,18 +401,32 @@ impl Installer {
         }))
     }
 
-    /// Recursively fetch a formula and all its dependencies in parallel batches
+    /// Recursively fetch a formula and all its dependencies in parallel batches.
+    /// Formulas are keyed by their own name; names that fetched a differently named
+    /// formula (aliases, old names, tap-qualified names) are returned alongside.
     async fn fetch_all_formulas(
         &self,
         names: &[String],
-    ) -> Result<BTreeMap<String, Formula>, Error> {
+        provided: Vec<Formula>,
+        selector: &BottleSelector,
+    ) -> Result<(BTreeMap<String, Formula>, BTreeMap<String, String>), Error> {
         use std::collections::HashSet;
-        use zb_core::select_bottle;
 
         let mut formulas = BTreeMap::new();
+        let mut renamed: BTreeMap<String, String> = BTreeMap::new();
         let mut fetched: HashSet<String> = HashSet::new();
         let mut to_fetch: Vec<String> = names.to_vec();
 
+        // Provided formulas take the place of any API formula of the same name
+        for formula in provided {
+            fetched.insert(formula.name.clone());
+            to_fetch.retain(|n| n != &formula.name);
+            to_fetch.extend(formula.dependencies.iter().cloned());
+            formulas.insert(formula.name.clone(), formula);
+        }
+        // Offline, keep resolving so every uncached formula is reported at once
+        let mut unavailable: Vec<String> = Vec::new();
+
         while !to_fetch.is_empty() {
             // Fetch current batch in parallel
             let batch: Vec<String> = to_fetch
@@ -199,15 +455,25 @@ impl Installer {
             for (i, result) in results.into_iter().enumerate() {
                 let formula = match result {
                     Ok(f) => f,
+                    Err(Error::OfflineUnavailable { missing }) => {
+                        unavailable.extend(missing);
+                        continue;
+                    }
                     Err(e) => return Err(e),
                 };
 
+                if formula.name != batch[i] {
+                    renamed.insert(batch[i].clone(), formula.name.clone());
+                    fetched.insert(formula.name.clone());
+                    to_fetch.retain(|n| n != &formula.name);
+                }
+
                 // Check if this formula has a bottle for the current platform
                 // If not, skip it (it's likely a system-provided dependency on this platform)
-                if select_bottle(&formula).is_err() {
+                if selector.select(&formula).is_err() {
                     eprintln!(
-                        "    Skipping {} (no bottle available for this platform)",
-                        formula.name
+                        "    Skipping {} (no bottle available for {})",
+                        formula.name, selector
                     );
                     continue;
                 }
@@ -219,11 +485,176 @@ impl Installer {
                     }
                 }
 
-                formulas.insert(batch[i].clone(), formula);
+                formulas.insert(formula.name.clone(), formula);
+            }
+        }
+
+        if !unavailable.is_empty() {
+            return Err(Error::OfflineUnavailable {
+                missing: unavailable,
+            });
+        }
+
+        // Point dependency edges at the names formulas were fetched under
+        for formula in formulas.values_mut() {
+            for dep in &mut formula.dependencies {
+                if let Some(name) = renamed.get(dep) {
+                    *dep = name.clone();
+                }
             }
         }
 
-        Ok(formulas)
+        Ok((formulas, renamed))
+    }
+
+    /// Describe a bottle tarball on disk as a formula that installs it. Name and
+    /// version come from its `{name}/{version}/` layout, dependencies from the
+    /// `runtime_dependencies` in its `INSTALL_RECEIPT.json` and the tag from a
+    /// Homebrew-style file name such as `jq--1.7.1.arm64_sonoma.bottle.tar.gz`,
+    /// defaulting to `all`. Nothing is cached until the formula's plan is executed.
+    pub fn local_bottle_formula(&self, path: &Path) -> Result<Formula, Error> {
+        let file_error = |e: std::io::Error| Error::FileError {
+            message: format!("failed to read bottle {}: {e}", path.display()),
+        };
+
+        let sha256 = {
+            use sha2::{Digest, Sha256};
+            let mut file = std::fs::File::open(path).map_err(file_error)?;
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut file, &mut hasher).map_err(file_error)?;
+            format!("{:x}", hasher.finalize())
+        };
+
+        let contents = read_bottle_contents(path)?;
+        let dependencies = contents.dependencies.ok_or_else(|| Error::FileError {
+            message: format!(
+                "{} has no INSTALL_RECEIPT.json listing its runtime dependencies; \
+                 install it with --formula-json and a formula JSON describing it",
+                path.display()
+            ),
+        })?;
+        let (name, version) = (contents.name, contents.version);
+
+        let tag = path
+            .file_name()
+            .and_then(|name| bottle_tag_from_file_name(&name.to_string_lossy()))
+            .unwrap_or_else(|| "all".to_string());
+        let url = match std::fs::canonicalize(path) {
+            Ok(absolute) => format!("file://{}", absolute.display()),
+            Err(_) => format!("file://{}", path.display()),
+        };
+
+        Ok(Formula {
+            name,
+            versions: Versions { stable: version },
+            dependencies,
+            bottle: Bottle {
+                stable: BottleStable {
+                    files: BTreeMap::from([(tag, BottleFile { url, sha256 })]),
+                    rebuild: 0,
+                },
+            },
+            revision: 0,
+            desc: None,
+            aliases: Vec::new(),
+            oldnames: Vec::new(),
+        })
+    }
+
+    /// Re-package an installed keg as a relocatable bottle in `output_dir`, next to
+    /// a formula JSON fragment for it. Its URL is `{root_url}/{file name}`, or a
+    /// `file://` URL to the bottle when no root URL is given. Without an explicit
+    /// `tag` the bottle is tagged for the running OS release.
+    pub fn bottle(
+        &self,
+        name: &str,
+        output_dir: &Path,
+        root_url: Option<&str>,
+        tag: Option<&str>,
+    ) -> Result<BuiltBottle, Error> {
+        let keg = self.db.get_installed(name).ok_or(Error::NotInstalled {
+            name: name.to_string(),
+        })?;
+        let tag = match tag {
+            Some(tag)
+                if !tag.is_empty()
+                    && tag
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') =>
+            {
+                tag.to_string()
+            }
+            Some(tag) => {
+                return Err(Error::InvalidArgument {
+                    message: format!("invalid bottle tag '{tag}'"),
+                });
+            }
+            None => host_bottle_tag()?,
+        };
+        let file_stem = format!("{}--{}.{tag}.bottle", keg.name, keg.version);
+
+        std::fs::create_dir_all(output_dir).map_err(|e| Error::FileError {
+            message: format!("failed to create {}: {e}", output_dir.display()),
+        })?;
+        let path = output_dir.join(format!("{file_stem}.tar.gz"));
+        let (prefix, cellar) = self.cellar.placeholder_paths();
+        let sha256 = write_bottle(
+            &self.cellar.keg_path(&keg.name, &keg.version),
+            &keg.name,
+            &keg.version,
+            &prefix,
+            &cellar,
+            &path,
+        )?;
+
+        let url = match root_url {
+            Some(root) => format!("{}/{file_stem}.tar.gz", root.trim_end_matches('/')),
+            None => {
+                let absolute = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                format!("file://{}", absolute.display())
+            }
+        };
+
+        // Installed versions already include any revision, so the fragment carries none
+        let formula = Formula {
+            name: keg.name.clone(),
+            versions: Versions {
+                stable: keg.version.clone(),
+            },
+            dependencies: self.db.get_dependencies(&keg.name)?,
+            bottle: Bottle {
+                stable: BottleStable {
+                    files: BTreeMap::from([(
+                        tag.clone(),
+                        BottleFile {
+                            url,
+                            sha256: sha256.clone(),
+                        },
+                    )]),
+                    rebuild: 0,
+                },
+            },
+            revision: 0,
+            desc: None,
+            aliases: Vec::new(),
+            oldnames: Vec::new(),
+        };
+
+        let json_path = output_dir.join(format!("{file_stem}.json"));
+        let json = serde_json::to_string_pretty(&formula).map_err(|e| Error::FileError {
+            message: format!("failed to serialize formula {}: {e}", keg.name),
+        })?;
+        std::fs::write(&json_path, json + "\n").map_err(|e| Error::FileError {
+            message: format!("failed to write {}: {e}", json_path.display()),
+        })?;
+
+        Ok(BuiltBottle {
+            path,
+            json_path,
+            formula,
+            tag,
+            sha256,
+        })
     }
 
     /// Execute the install plan
@@ -245,25 +676,40 @@ impl Installer {
             }
         };
 
+        let requested = plan.requested;
+        let skip_link = plan.skip_link;
+        let taps = plan.taps;
+        let planned: BTreeSet<String> = plan.formulas.iter().map(|f| f.name.clone()).collect();
+
         // Pair formulas with bottles
-        let to_install: Vec<(Formula, SelectedBottle)> = plan
-            .formulas
-            .into_iter()
-            .zip(plan.bottles.into_iter())
-            .collect();
+        let to_install: Vec<(Formula, SelectedBottle)> =
+            plan.formulas.into_iter().zip(plan.bottles).collect();
 
         if to_install.is_empty() {
             return Ok(ExecuteResult { installed: 0 });
         }
 
+        if self.offline {
+            let missing: Vec<String> = to_install
+                .iter()
+                .filter(|(_, b)| !self.downloader.has_blob(&b.sha256))
+                .map(|(f, b)| {
+                    format!(
+                        "bottle {} ({})",
+                        f.name,
+                        &b.sha256[..12.min(b.sha256.len())]
+                    )
+                })
+                .collect();
+            if !missing.is_empty() {
+                return Err(Error::OfflineUnavailable { missing });
+            }
+        }
+
         // Download all bottles
         let requests: Vec<DownloadRequest> = to_install
             .iter()
-            .map(|(f, b)| DownloadRequest {
-                url: b.url.clone(),
-                sha256: b.sha256.clone(),
-                name: f.name.clone(),
-            })
+            .map(|(f, b)| self.download_request(f, b))
             .collect();
 
         // Convert progress callback for download
@@ -324,8 +770,25 @@ impl Installer {
                         name: formula.name.clone(),
                     });
 
+                    // A different version already installed is being upgraded in place:
+                    // its links have to go before the new keg can claim the same paths
+                    let replaced_version = self
+                        .db
+                        .get_installed(&formula.name)
+                        .map(|keg| keg.version)
+                        .filter(|v| *v != formula.effective_version());
+                    let old_keg_path = replaced_version
+                        .as_ref()
+                        .map(|v| self.cellar.keg_path(&formula.name, v));
+                    if let Some(ref old_keg_path) = old_keg_path
+                        && let Err(e) = self.linker.unlink_keg(old_keg_path)
+                    {
+                        error = Some(e);
+                        continue;
+                    }
+
                     // Link executables if requested
-                    let linked_files = if link {
+                    let linked_files = if link && !skip_link.contains(&formula.name) {
                         report(InstallProgress::LinkStarted {
                             name: formula.name.clone(),
                         });
@@ -337,6 +800,11 @@ impl Installer {
                                 files
                             }
                             Err(e) => {
+                                // Best effort: leave the old version usable
+                                if let Some(ref old_keg_path) = old_keg_path {
+                                    let _ = self.linker.unlink_keg(&keg_path);
+                                    let _ = self.linker.link_keg(old_keg_path);
+                                }
                                 error = Some(e);
                                 continue;
                             }
@@ -355,6 +823,21 @@ impl Installer {
                         version: formula.effective_version(),
                         store_key: bottle.sha256.clone(),
                         linked_files,
+                        replaced_version,
+                        // Dependencies skipped for lack of a bottle are provided by the system
+                        dependencies: formula
+                            .dependencies
+                            .iter()
+                            .filter(|dep| planned.contains(*dep))
+                            .cloned()
+                            .collect(),
+                        aliases: formula
+                            .aliases
+                            .iter()
+                            .chain(&formula.oldnames)
+                            .cloned()
+                            .collect(),
+                        tap: taps.get(&formula.name).cloned(),
                     });
                 }
                 Err(e) => {
@@ -368,10 +851,19 @@ impl Installer {
             return Err(e);
         }
 
-        // Record all successful installs in database (in order)
+        // Record all successful installs in database (in order).
+        // Recording over an existing keg moves its row and store ref in the same transaction.
         for processed in completed.into_iter().flatten() {
             let tx = self.db.transaction()?;
-            tx.record_install(&processed.name, &processed.version, &processed.store_key)?;
+            tx.record_install(
+                &processed.name,
+                &processed.version,
+                &processed.store_key,
+                requested.contains(&processed.name),
+            )?;
+            tx.record_dependencies(&processed.name, &processed.dependencies)?;
+            tx.record_aliases(&processed.name, &processed.aliases)?;
+            tx.record_tap(&processed.name, processed.tap.as_deref())?;
 
             for linked in &processed.linked_files {
                 tx.record_linked_file(
@@ -383,6 +875,11 @@ impl Installer {
             }
 
             tx.commit()?;
+
+            // The replaced keg is only removed once the new one is on record
+            if let Some(ref old_version) = processed.replaced_version {
+                self.cellar.remove_keg(&processed.name, old_version)?;
+            }
         }
 
         Ok(ExecuteResult {
@@ -390,19 +887,138 @@ impl Installer {
         })
     }
 
+    /// Download and verify every bottle in a plan into the blob cache without
+    /// installing anything. Returns the cached blob paths in plan order.
+    pub async fn fetch_bottles(
+        &self,
+        plan: &InstallPlan,
+        progress: Option<DownloadProgressCallback>,
+    ) -> Result<Vec<std::path::PathBuf>, Error> {
+        let requests: Vec<DownloadRequest> = plan
+            .formulas
+            .iter()
+            .zip(&plan.bottles)
+            .map(|(f, b)| self.download_request(f, b))
+            .collect();
+
+        self.downloader
+            .download_all_with_progress(requests, progress)
+            .await
+    }
+
+    /// Unpack fetched bottles into the store without materializing kegs or
+    /// linking anything. `blobs` pairs with the plan's bottles by index.
+    pub async fn unpack_bottles(
+        &self,
+        plan: &InstallPlan,
+        blobs: &[std::path::PathBuf],
+    ) -> Result<Vec<std::path::PathBuf>, Error> {
+        let mut entries = Vec::with_capacity(blobs.len());
+        for (index, ((formula, bottle), blob_path)) in plan
+            .formulas
+            .iter()
+            .zip(&plan.bottles)
+            .zip(blobs)
+            .enumerate()
+        {
+            let download = DownloadResult {
+                name: formula.name.clone(),
+                sha256: bottle.sha256.clone(),
+                blob_path: blob_path.clone(),
+                index,
+            };
+            entries.push(
+                self.extract_with_retry(&download, formula, bottle, None)
+                    .await?,
+            );
+        }
+        Ok(entries)
+    }
+
+    /// Bundle a plan's bottles and formula JSON into an archive at `path` for
+    /// machines without network access, fetching bottles that aren't cached yet
+    pub async fn export_cache(
+        &self,
+        plan: &InstallPlan,
+        path: &Path,
+    ) -> Result<CacheArchive, Error> {
+        self.fetch_bottles(plan, None).await?;
+
+        let bottles = plan
+            .formulas
+            .iter()
+            .zip(&plan.bottles)
+            .map(|(formula, bottle)| ArchivedBottle {
+                name: formula.name.clone(),
+                version: formula.effective_version(),
+                tag: bottle.tag.clone(),
+                sha256: bottle.sha256.clone(),
+            })
+            .collect();
+
+        let mut formulas = BTreeMap::new();
+        for formula in &plan.formulas {
+            let body = serde_json::to_string(formula).map_err(|e| Error::FileError {
+                message: format!("failed to serialize formula {}: {e}", formula.name),
+            })?;
+            formulas.insert(formula.name.clone(), body);
+        }
+
+        let archive = CacheArchive {
+            manifest: CacheManifest {
+                format: CACHE_ARCHIVE_FORMAT,
+                renamed: plan.renamed.clone(),
+                bottles,
+            },
+            formulas,
+        };
+        write_cache_archive(path, &archive, self.downloader.blob_cache())?;
+        Ok(archive)
+    }
+
+    /// Load an archive written by `export_cache` into the blob and API caches so
+    /// its formulas install offline. Also returns how many bottles were new.
+    pub fn import_cache(&self, path: &Path) -> Result<(CacheArchive, usize), Error> {
+        let (archive, added) = import_cache_archive(path, self.downloader.blob_cache())?;
+
+        for (name, body) in &archive.formulas {
+            self.api_client.seed_formula(name, body)?;
+        }
+        // Aliases have no endpoint of their own, but caching the canonical JSON
+        // under them lets them resolve offline without the formula index
+        for (alias, canonical) in &archive.manifest.renamed {
+            if let Some(body) = archive.formulas.get(canonical) {
+                self.api_client.seed_formula(alias, body)?;
+            }
+        }
+
+        Ok((archive, added))
+    }
+
     /// Convenience method to plan and execute in one call
     pub async fn install(&mut self, names: &[String], link: bool) -> Result<ExecuteResult, Error> {
         let plan = self.plan(names).await?;
         self.execute(plan, link).await
     }
 
-    /// Uninstall a formula
-    pub fn uninstall(&mut self, name: &str) -> Result<(), Error> {
+    /// Uninstall a formula. Fails if other installed kegs depend on it,
+    /// unless `ignore_dependencies` is set.
+    pub fn uninstall(&mut self, name: &str, ignore_dependencies: bool) -> Result<(), Error> {
         // Check if installed
         let installed = self.db.get_installed(name).ok_or(Error::NotInstalled {
             name: name.to_string(),
         })?;
 
+        if !ignore_dependencies {
+            let dependents = self.db.get_dependents(name)?;
+            if !dependents.is_empty() {
+                return Err(Error::HasDependents {
+                    name: name.to_string(),
+                    dependents,
+                });
+            }
+        }
+
         // Unlink executables
         let keg_path = self.cellar.keg_path(name, &installed.version);
         self.linker.unlink_keg(&keg_path)?;
@@ -420,6 +1036,166 @@ impl Installer {
         Ok(())
     }
 
+    /// Search the cached formula index by name, alias and description
+    pub async fn search(&self, query: &str, desc_only: bool) -> Result<Vec<SearchResult>, Error> {
+        let index = self.api_client.formula_index().await?;
+
+        Ok(search_formulas(index.values(), query, desc_only)
+            .into_iter()
+            .map(|formula| SearchResult {
+                name: formula.name.clone(),
+                version: formula.effective_version(),
+                desc: formula.desc.clone(),
+                installed: self.db.get_installed(&formula.name).is_some(),
+            })
+            .collect())
+    }
+
+    /// Compare installed kegs against the formula API, or the tap they came from.
+    /// An empty `names` slice checks every installed keg. Tap kegs are reported
+    /// as `user/repo/name` so upgrading them stays with their tap.
+    pub async fn outdated(&self, names: &[String]) -> Result<Vec<OutdatedPackage>, Error> {
+        let kegs = if names.is_empty() {
+            self.db.list_installed()?
+        } else {
+            names
+                .iter()
+                .map(|name| {
+                    self.db
+                        .resolve_installed_name(name)
+                        .and_then(|installed| self.db.get_installed(&installed))
+                        .ok_or_else(|| Error::NotInstalled { name: name.clone() })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let formula_names: Vec<String> = kegs.iter().map(|keg| keg.formula_name()).collect();
+        let futures: Vec<_> = formula_names
+            .iter()
+            .map(|name| self.api_client.get_formula(name))
+            .collect();
+        let results = futures::future::join_all(futures).await;
+
+        let mut outdated = Vec::new();
+        for (keg, result) in kegs.into_iter().zip(results) {
+            let formula = match result {
+                Ok(f) => f,
+                Err(Error::MissingFormula { name }) => {
+                    eprintln!("    Skipping {name} (no longer available from the formula API)");
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let current_version = formula.effective_version();
+            if Version::parse(&keg.version) < Version::parse(&current_version) {
+                outdated.push(OutdatedPackage {
+                    name: keg.formula_name(),
+                    installed_version: keg.version,
+                    current_version,
+                });
+            }
+        }
+
+        Ok(outdated)
+    }
+
+    /// Installed kegs that directly depend on `name`
+    pub fn dependents(&self, name: &str) -> Result<Vec<String>, Error> {
+        self.db.get_dependents(name)
+    }
+
+    /// Installed kegs that depend on `name` directly or transitively, sorted by name
+    pub fn uses(&self, name: &str) -> Result<Vec<String>, Error> {
+        if self.db.get_installed(name).is_none() {
+            return Err(Error::NotInstalled {
+                name: name.to_string(),
+            });
+        }
+
+        let mut users: BTreeSet<String> = BTreeSet::new();
+        let mut to_visit = self.db.get_dependents(name)?;
+        while let Some(dependent) = to_visit.pop() {
+            if dependent != name && users.insert(dependent.clone()) {
+                to_visit.extend(self.db.get_dependents(&dependent)?);
+            }
+        }
+
+        Ok(users.into_iter().collect())
+    }
+
+    /// Dependency chains explaining why `name` is installed. Each chain starts at a
+    /// requested keg and ends at `name`; a requested keg is its own single-entry chain.
+    pub fn why(&self, name: &str) -> Result<Vec<Vec<String>>, Error> {
+        if self.db.get_installed(name).is_none() {
+            return Err(Error::NotInstalled {
+                name: name.to_string(),
+            });
+        }
+
+        let mut chains = Vec::new();
+        let mut path = vec![name.to_string()];
+        self.collect_why_chains(&mut path, &mut chains)?;
+
+        for chain in &mut chains {
+            chain.reverse();
+        }
+        chains.sort();
+        Ok(chains)
+    }
+
+    /// Walk dependents upwards from the end of `path` until reaching requested kegs
+    fn collect_why_chains(
+        &self,
+        path: &mut Vec<String>,
+        chains: &mut Vec<Vec<String>>,
+    ) -> Result<(), Error> {
+        let current = path.last().cloned().unwrap_or_default();
+        if self
+            .db
+            .get_installed(&current)
+            .is_some_and(|keg| keg.requested)
+        {
+            chains.push(path.clone());
+            return Ok(());
+        }
+
+        for dependent in self.db.get_dependents(&current)? {
+            // Guard against cycles in recorded edges
+            if path.contains(&dependent) {
+                continue;
+            }
+            path.push(dependent);
+            self.collect_why_chains(path, chains)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Find dependency-only kegs that no requested keg needs anymore
+    pub fn orphaned(&self) -> Result<Vec<InstalledKeg>, Error> {
+        let installed = self.db.list_installed()?;
+
+        // Walk the recorded dependency edges out from every requested keg
+        let mut needed: BTreeSet<String> = BTreeSet::new();
+        let mut to_visit: Vec<String> = installed
+            .iter()
+            .filter(|keg| keg.requested)
+            .map(|keg| keg.name.clone())
+            .collect();
+        while let Some(name) = to_visit.pop() {
+            if needed.insert(name.clone()) {
+                to_visit.extend(self.db.get_dependencies(&name)?);
+            }
+        }
+
+        Ok(installed
+            .into_iter()
+            .filter(|keg| !needed.contains(&keg.name))
+            .collect())
+    }
+
     /// Garbage collect unreferenced store entries
     pub fn gc(&mut self) -> Result<Vec<String>, Error> {
         let unreferenced = self.db.get_unreferenced_store_keys()?;
@@ -438,13 +1214,65 @@ impl Installer {
         self.db.get_installed(name).is_some()
     }
 
+    /// Register a third-party tap serving formula JSON from a local directory or an
+    /// HTTP base URL. Formulas in it are then installable as `user/repo/formula`.
+    pub fn add_tap(&mut self, name: &str, source: &str) -> Result<(), Error> {
+        if !is_tap_name(name) {
+            return Err(Error::InvalidArgument {
+                message: format!("tap names look like 'user/repo', got '{name}'"),
+            });
+        }
+
+        let source = if is_url(source) {
+            source.trim_end_matches('/').to_string()
+        } else {
+            let path = Path::new(source.trim_start_matches("file://"));
+            if !path.is_dir() {
+                return Err(Error::InvalidArgument {
+                    message: format!("tap source '{source}' is neither a URL nor a directory"),
+                });
+            }
+            path.canonicalize()
+                .map_err(|e| Error::FileError {
+                    message: format!("failed to resolve {}: {e}", path.display()),
+                })?
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        self.db.add_tap(name, &source)?;
+        self.api_client.set_taps(self.db.list_taps()?);
+        Ok(())
+    }
+
+    /// Unregister a tap; its formulas are no longer resolvable
+    pub fn remove_tap(&mut self, name: &str) -> Result<(), Error> {
+        if !self.db.remove_tap(name)? {
+            return Err(Error::InvalidArgument {
+                message: format!("tap '{name}' is not registered"),
+            });
+        }
+        self.api_client.set_taps(self.db.list_taps()?);
+        Ok(())
+    }
+
+    /// Registered taps and the source each serves formulas from
+    pub fn list_taps(&self) -> Result<BTreeMap<String, String>, Error> {
+        self.db.list_taps()
+    }
+
+    /// The installed keg a name refers to, following aliases and old names
+    pub fn resolve_installed_name(&self, name: &str) -> Option<String> {
+        self.db.resolve_installed_name(name)
+    }
+
     /// Get info about an installed formula
-    pub fn get_installed(&self, name: &str) -> Option<crate::storage::db::InstalledKeg> {
+    pub fn get_installed(&self, name: &str) -> Option<InstalledKeg> {
         self.db.get_installed(name)
     }
 
     /// List all installed formulas
-    pub fn list_installed(&self) -> Result<Vec<crate::storage::db::InstalledKeg>, Error> {
+    pub fn list_installed(&self) -> Result<Vec<InstalledKeg>, Error> {
         self.db.list_installed()
     }
 
@@ -454,24 +1282,161 @@ impl Installer {
     }
 }
 
-/// Create an Installer with standard paths
-pub fn create_installer(
-    root: &Path,
-    prefix: &Path,
-    concurrency: usize,
-) -> Result<Installer, Error> {
-    use std::fs;
+/// Formulas fetched through a `user/repo/name` name, mapped to their tap
+fn taps_from_renamed(renamed: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    renamed
+        .iter()
+        .filter_map(|(name, canonical)| {
+            let (tap, _) = split_tap_name(name)?;
+            Some((canonical.clone(), tap.to_string()))
+        })
+        .collect()
+}
 
-    // First ensure the root directory exists
-    if !root.exists() {
-        fs::create_dir_all(root).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::PermissionDenied {
-                Error::StoreCorruption {
-                    message: format!(
-                        "cannot create root directory '{}': permission denied.\n\n\
-                        Create it with:\n  sudo mkdir -p {} && sudo chown $USER {}",
-                        root.display(),
-                        root.display(),
+/// What a bottle tarball says about itself
+struct BottleContents {
+    name: String,
+    version: String,
+    /// Direct runtime dependencies from `INSTALL_RECEIPT.json`, if it lists them
+    dependencies: Option<Vec<String>>,
+}
+
+/// Read a bottle laid out as `{name}/{version}/` without unpacking it
+fn read_bottle_contents(path: &Path) -> Result<BottleContents, Error> {
+    let read_error = |e: std::io::Error| Error::FileError {
+        message: format!("failed to read bottle {}: {e}", path.display()),
+    };
+    let not_a_bottle = || Error::FileError {
+        message: format!(
+            "{} does not look like a bottle: expected a single {{name}}/{{version}}/ directory",
+            path.display()
+        ),
+    };
+
+    let mut archive = tar::Archive::new(crate::extraction::open_tarball(path)?);
+    let mut layout: Option<(String, String)> = None;
+    let mut receipt = None;
+    for entry in archive.entries().map_err(read_error)? {
+        let mut entry = entry.map_err(read_error)?;
+        let entry_path = entry.path().map_err(read_error)?.into_owned();
+        let parts: Vec<String> = entry_path
+            .components()
+            .filter_map(|c| match c {
+                std::path::Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+        let [name, version, rest @ ..] = parts.as_slice() else {
+            continue;
+        };
+
+        match &layout {
+            Some((n, v)) if n != name || v != version => return Err(not_a_bottle()),
+            Some(_) => {}
+            None => layout = Some((name.clone(), version.clone())),
+        }
+        if rest == ["INSTALL_RECEIPT.json"] {
+            let mut body = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut body).map_err(read_error)?;
+            receipt = Some(body);
+        }
+    }
+
+    let (name, version) = layout.ok_or_else(not_a_bottle)?;
+    let dependencies = match receipt {
+        Some(body) => runtime_dependencies(&body).map_err(|e| Error::FileError {
+            message: format!("invalid INSTALL_RECEIPT.json in {}: {e}", path.display()),
+        })?,
+        None => None,
+    };
+    Ok(BottleContents {
+        name,
+        version,
+        dependencies,
+    })
+}
+
+/// The dependencies a Homebrew install receipt records for its keg. Receipts list
+/// the whole runtime closure; only the ones declared directly become edges, since
+/// the rest are resolved through them.
+fn runtime_dependencies(receipt: &str) -> Result<Option<Vec<String>>, serde_json::Error> {
+    #[derive(serde::Deserialize)]
+    struct Receipt {
+        runtime_dependencies: Option<Vec<RuntimeDependency>>,
+    }
+    #[derive(serde::Deserialize)]
+    struct RuntimeDependency {
+        full_name: String,
+        #[serde(default = "declared_directly_default")]
+        declared_directly: bool,
+    }
+    fn declared_directly_default() -> bool {
+        true
+    }
+
+    let receipt: Receipt = serde_json::from_str(receipt)?;
+    Ok(receipt.runtime_dependencies.map(|deps| {
+        deps.into_iter()
+            .filter(|dep| dep.declared_directly)
+            .map(|dep| dep.full_name)
+            .collect()
+    }))
+}
+
+/// The bottle tag for the machine zerobrew is running on, including its macOS release
+fn host_bottle_tag() -> Result<String, Error> {
+    let target = BottleTarget::host();
+    let release = match target.os {
+        TargetOs::MacOs => std::process::Command::new("sw_vers")
+            .arg("-productVersion")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_default(),
+        TargetOs::Linux => String::new(),
+    };
+    target
+        .tag_for_release(&release)
+        .ok_or_else(|| Error::InvalidArgument {
+            message: format!(
+                "could not determine the bottle tag for macOS '{release}'; pass an explicit tag"
+            ),
+        })
+}
+
+/// The tag in a bottle file name: `{name}--{version}.{tag}.bottle[.{rebuild}].tar.gz`
+fn bottle_tag_from_file_name(file_name: &str) -> Option<String> {
+    let (rest, rebuild) = file_name.strip_suffix(".tar.gz")?.rsplit_once(".bottle")?;
+    let valid_rebuild = rebuild.is_empty()
+        || rebuild
+            .strip_prefix('.')
+            .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    if !valid_rebuild {
+        return None;
+    }
+    let (_, tag) = rest.rsplit_once('.')?;
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// Create an Installer with standard paths
+pub fn create_installer(
+    root: &Path,
+    prefix: &Path,
+    concurrency: usize,
+) -> Result<Installer, Error> {
+    use std::fs;
+
+    // First ensure the root directory exists
+    if !root.exists() {
+        fs::create_dir_all(root).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                Error::StoreCorruption {
+                    message: format!(
+                        "cannot create root directory '{}': permission denied.\n\n\
+                        Create it with:\n  sudo mkdir -p {} && sudo chown $USER {}",
+                        root.display(),
+                        root.display(),
                         root.display()
                     ),
                 }
@@ -488,10 +1453,14 @@ pub fn create_installer(
         message: format!("failed to create db directory: {e}"),
     })?;
 
-    let api_client = ApiClient::new();
     let blob_cache = BlobCache::new(&root.join("cache")).map_err(|e| Error::StoreCorruption {
         message: format!("failed to create blob cache: {e}"),
     })?;
+    // The API cache (next to the blob cache) only saves bandwidth, so run without it rather than fail
+    let mut api_client = ApiClient::new();
+    if let Ok(api_cache) = ApiCache::open(&root.join("cache/api.sqlite3")) {
+        api_client = api_client.with_cache(api_cache);
+    }
     let store = Store::new(root).map_err(|e| Error::StoreCorruption {
         message: format!("failed to create store: {e}"),
     })?;
@@ -503,6 +1472,7 @@ pub fn create_installer(
         message: format!("failed to create linker: {e}"),
     })?;
     let db = Database::open(&root.join("db/zb.sqlite3"))?;
+    api_client.set_taps(db.list_taps()?);
 
     use crate::network::download::ParallelDownloader;
     let parallel_downloader = ParallelDownloader::with_concurrency(blob_cache, concurrency);
@@ -514,6 +1484,8 @@ pub fn create_installer(
         cellar,
         linker,
         db,
+        offline: false,
+        bottle_domain: domain_from_env("ZEROBREW_BOTTLE_DOMAIN", "HOMEBREW_BOTTLE_DOMAIN"),
     })
 }
 
@@ -552,6 +1524,65 @@ mod tests {
         encoder.finish().unwrap()
     }
 
+    fn create_versioned_bottle_tarball(formula_name: &str, version: &str) -> Vec<u8> {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+        use tar::Builder;
+
+        let mut builder = Builder::new(Vec::new());
+
+        let content = format!("#!/bin/sh\necho {formula_name} {version}");
+        let mut header = tar::Header::new_gnu();
+        header
+            .set_path(format!("{formula_name}/{version}/bin/{formula_name}"))
+            .unwrap();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append(&header, content.as_bytes()).unwrap();
+
+        let tar_data = builder.into_inner().unwrap();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tar_data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// A bottle with an `INSTALL_RECEIPT.json` listing `deps` as direct runtime dependencies
+    fn create_bottle_with_receipt(formula_name: &str, version: &str, deps: &[&str]) -> Vec<u8> {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+        use tar::Builder;
+
+        let runtime_dependencies: Vec<serde_json::Value> = deps
+            .iter()
+            .map(|dep| serde_json::json!({ "full_name": dep, "declared_directly": true }))
+            .collect();
+        let receipt = serde_json::json!({ "runtime_dependencies": runtime_dependencies });
+        let script = format!("#!/bin/sh\necho {formula_name} {version}");
+
+        let mut builder = Builder::new(Vec::new());
+        for (file, content) in [
+            (format!("bin/{formula_name}"), script),
+            ("INSTALL_RECEIPT.json".to_string(), receipt.to_string()),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header
+                .set_path(format!("{formula_name}/{version}/{file}"))
+                .unwrap();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append(&header, content.as_bytes()).unwrap();
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        encoder.finish().unwrap()
+    }
+
     fn sha256_hex(data: &[u8]) -> String {
         use sha2::{Digest, Sha256};
         let mut hasher = Sha256::new();
@@ -560,13 +1591,113 @@ mod tests {
     }
 
     fn get_test_bottle_tag() -> &'static str {
-        if cfg!(target_os = "linux") {
-            "x86_64_linux"
-        } else if cfg!(target_arch = "x86_64") {
-            "sonoma"
-        } else {
-            "arm64_sonoma"
-        }
+        zb_core::BottleTarget::host().preferred_tags()[0]
+    }
+
+    fn formula_json(base_url: &str, name: &str, version: &str, deps: &[&str], sha: &str) -> String {
+        let tag = get_test_bottle_tag();
+        let deps = deps
+            .iter()
+            .map(|d| format!("\"{d}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            r#"{{
+                "name": "{name}",
+                "versions": {{ "stable": "{version}" }},
+                "dependencies": [{deps}],
+                "bottle": {{
+                    "stable": {{
+                        "files": {{
+                            "{tag}": {{
+                                "url": "{base_url}/bottles/{name}-{version}.{tag}.bottle.tar.gz",
+                                "sha256": "{sha}"
+                            }}
+                        }}
+                    }}
+                }}
+            }}"#
+        )
+    }
+
+    async fn mount_formula(
+        mock_server: &MockServer,
+        name: &str,
+        version: &str,
+        deps: &[&str],
+        bottle: &[u8],
+    ) {
+        let tag = get_test_bottle_tag();
+        let json = formula_json(&mock_server.uri(), name, version, deps, &sha256_hex(bottle));
+
+        Mock::given(method("GET"))
+            .and(path(format!("/{name}.json")))
+            .respond_with(ResponseTemplate::new(200).set_body_string(json))
+            .mount(mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/bottles/{name}-{version}.{tag}.bottle.tar.gz"
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(bottle.to_vec()))
+            .mount(mock_server)
+            .await;
+    }
+
+    fn test_installer(tmp: &TempDir, mock_server: &MockServer) -> Installer {
+        let root = tmp.path().join("zerobrew");
+        let prefix = tmp.path().join("homebrew");
+        fs::create_dir_all(root.join("db")).unwrap();
+
+        let api_client = ApiClient::with_base_url(mock_server.uri());
+        let blob_cache = BlobCache::new(&root.join("cache")).unwrap();
+        let store = Store::new(&root).unwrap();
+        let cellar = Cellar::new(&root).unwrap();
+        let linker = Linker::new(&prefix).unwrap();
+        let db = Database::open(&root.join("db/zb.sqlite3")).unwrap();
+
+        Installer::new(api_client, blob_cache, store, cellar, linker, db)
+    }
+
+    #[tokio::test]
+    async fn outdated_reports_kegs_behind_the_api() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let old_bottle = create_bottle_tarball("stale");
+        let fresh_bottle = create_bottle_tarball("fresh");
+        mount_formula(&mock_server, "stale", "1.0.0", &[], &old_bottle).await;
+        mount_formula(&mock_server, "fresh", "1.0.0", &[], &fresh_bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer
+            .install(&["stale".to_string(), "fresh".to_string()], true)
+            .await
+            .unwrap();
+
+        assert!(installer.outdated(&[]).await.unwrap().is_empty());
+
+        // The API now serves a newer stale, and an older fresh which is not an upgrade
+        mock_server.reset().await;
+        mount_formula(&mock_server, "stale", "1.1.0", &[], &old_bottle).await;
+        mount_formula(&mock_server, "fresh", "0.9.0", &[], &fresh_bottle).await;
+
+        let outdated = installer.outdated(&[]).await.unwrap();
+        assert_eq!(
+            outdated,
+            vec![OutdatedPackage {
+                name: "stale".to_string(),
+                installed_version: "1.0.0".to_string(),
+                current_version: "1.1.0".to_string(),
+            }]
+        );
+
+        let err = installer
+            .outdated(&["missing".to_string()])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::NotInstalled { name } if name == "missing"));
     }
 
     #[tokio::test]
@@ -651,6 +1782,947 @@ mod tests {
         assert_eq!(installed.unwrap().version, "1.0.0");
     }
 
+    #[tokio::test]
+    async fn upgrade_replaces_keg_and_moves_store_ref() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let old_bottle = create_versioned_bottle_tarball("upme", "1.0.0");
+        let new_bottle = create_versioned_bottle_tarball("upme", "2.0.0");
+        mount_formula(&mock_server, "upme", "1.0.0", &[], &old_bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer
+            .install(&["upme".to_string()], true)
+            .await
+            .unwrap();
+
+        mock_server.reset().await;
+        mount_formula(&mock_server, "upme", "2.0.0", &[], &new_bottle).await;
+
+        let outdated = installer.outdated(&[]).await.unwrap();
+        assert_eq!(outdated.len(), 1);
+
+        let plan = installer.plan(&["upme".to_string()]).await.unwrap();
+        installer.execute(plan, true).await.unwrap();
+
+        let root = tmp.path().join("zerobrew");
+        let prefix = tmp.path().join("homebrew");
+        assert!(root.join("cellar/upme/2.0.0").exists());
+        assert!(!root.join("cellar/upme/1.0.0").exists());
+        assert_eq!(
+            fs::read_to_string(prefix.join("bin/upme")).unwrap(),
+            "#!/bin/sh\necho upme 2.0.0"
+        );
+
+        let installed = installer.get_installed("upme").unwrap();
+        assert_eq!(installed.version, "2.0.0");
+        assert_eq!(installed.store_key, sha256_hex(&new_bottle));
+        assert_eq!(installer.db.get_store_refcount(&sha256_hex(&old_bottle)), 0);
+        assert_eq!(installer.db.get_store_refcount(&sha256_hex(&new_bottle)), 1);
+        assert!(installer.outdated(&[]).await.unwrap().is_empty());
+
+        // The old store entry is now collectable
+        assert_eq!(installer.gc().unwrap(), vec![sha256_hex(&old_bottle)]);
+    }
+
+    #[tokio::test]
+    async fn uninstall_guards_dependencies_and_finds_orphans() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let app_bottle = create_versioned_bottle_tarball("app", "1.0.0");
+        let lib_bottle = create_versioned_bottle_tarball("libapp", "1.0.0");
+        let tool_bottle = create_versioned_bottle_tarball("tool", "1.0.0");
+        mount_formula(&mock_server, "app", "1.0.0", &["libapp"], &app_bottle).await;
+        mount_formula(&mock_server, "libapp", "1.0.0", &[], &lib_bottle).await;
+        mount_formula(&mock_server, "tool", "1.0.0", &[], &tool_bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer
+            .install(&["app".to_string(), "tool".to_string()], true)
+            .await
+            .unwrap();
+
+        assert!(installer.get_installed("app").unwrap().requested);
+        assert!(!installer.get_installed("libapp").unwrap().requested);
+        assert!(installer.orphaned().unwrap().is_empty());
+
+        let err = installer.uninstall("libapp", false).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::HasDependents { name, dependents }
+                if name == "libapp" && dependents == vec!["app".to_string()]
+        ));
+        assert!(installer.is_installed("libapp"));
+
+        installer.uninstall("app", false).unwrap();
+
+        let orphans: Vec<String> = installer
+            .orphaned()
+            .unwrap()
+            .into_iter()
+            .map(|keg| keg.name)
+            .collect();
+        assert_eq!(orphans, vec!["libapp".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn uses_and_why_follow_recorded_edges() {
+        // No mocks mounted: both queries must answer from the database alone
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+        let mut installer = test_installer(&tmp, &mock_server);
+
+        // curl and wget are requested; both reach openssl@3 through libssh2 or directly
+        {
+            let tx = installer.db.transaction().unwrap();
+            tx.record_install("openssl@3", "3.0.0", "k1", false)
+                .unwrap();
+            tx.record_install("libssh2", "1.0.0", "k2", false).unwrap();
+            tx.record_dependencies("libssh2", &["openssl@3".to_string()])
+                .unwrap();
+            tx.record_install("curl", "8.0.0", "k3", true).unwrap();
+            tx.record_dependencies("curl", &["libssh2".to_string(), "openssl@3".to_string()])
+                .unwrap();
+            tx.record_install("wget", "1.0.0", "k4", true).unwrap();
+            tx.record_dependencies("wget", &["openssl@3".to_string()])
+                .unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(
+            installer.uses("openssl@3").unwrap(),
+            vec!["curl", "libssh2", "wget"]
+        );
+        assert!(installer.uses("curl").unwrap().is_empty());
+
+        assert_eq!(
+            installer.why("openssl@3").unwrap(),
+            vec![
+                vec!["curl", "libssh2", "openssl@3"],
+                vec!["curl", "openssl@3"],
+                vec!["wget", "openssl@3"],
+            ]
+        );
+        assert_eq!(installer.why("curl").unwrap(), vec![vec!["curl"]]);
+
+        let graph = installer
+            .installed_dependency_graph(&["curl".to_string()])
+            .unwrap();
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph["curl"], vec!["libssh2", "openssl@3"]);
+        assert_eq!(installer.installed_dependency_graph(&[]).unwrap().len(), 4);
+        assert!(matches!(
+            installer.why("missing").unwrap_err(),
+            Error::NotInstalled { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn installs_from_lockfile_without_api() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let app_bottle = create_versioned_bottle_tarball("app", "1.0.0");
+        let lib_bottle = create_versioned_bottle_tarball("libapp", "1.0.0");
+        mount_formula(&mock_server, "app", "1.0.0", &["libapp"], &app_bottle).await;
+        mount_formula(&mock_server, "libapp", "1.0.0", &[], &lib_bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        let plan = installer.plan(&["app".to_string()]).await.unwrap();
+        let lockfile = Lockfile::new(&["app".to_string()], &plan.formulas, &plan.bottles);
+
+        // Only the bottles stay reachable; formula JSON requests would now 404
+        let tag = get_test_bottle_tag();
+        mock_server.reset().await;
+        for (name, bottle) in [("app", &app_bottle), ("libapp", &lib_bottle)] {
+            Mock::given(method("GET"))
+                .and(path(format!("/bottles/{name}-1.0.0.{tag}.bottle.tar.gz")))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(bottle.clone()))
+                .mount(&mock_server)
+                .await;
+        }
+
+        let plan = InstallPlan::from_lockfile(&lockfile).unwrap();
+        installer.execute(plan, true).await.unwrap();
+
+        assert!(installer.get_installed("app").unwrap().requested);
+        assert!(!installer.get_installed("libapp").unwrap().requested);
+        assert_eq!(installer.dependents("libapp").unwrap(), vec!["app"]);
+    }
+
+    #[tokio::test]
+    async fn skip_link_installs_without_linking() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let app_bottle = create_versioned_bottle_tarball("app", "1.0.0");
+        let tool_bottle = create_versioned_bottle_tarball("tool", "1.0.0");
+        mount_formula(&mock_server, "app", "1.0.0", &[], &app_bottle).await;
+        mount_formula(&mock_server, "tool", "1.0.0", &[], &tool_bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        let mut plan = installer
+            .plan(&["app".to_string(), "tool".to_string()])
+            .await
+            .unwrap();
+        plan.skip_link.insert("tool".to_string());
+        installer.execute(plan, true).await.unwrap();
+
+        let prefix = tmp.path().join("homebrew");
+        assert!(prefix.join("bin/app").exists());
+        assert!(!prefix.join("bin/tool").exists());
+        assert!(installer.is_installed("tool"));
+    }
+
+    #[tokio::test]
+    async fn offline_installs_from_cache_and_lists_what_is_missing() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let app_bottle = create_versioned_bottle_tarball("app", "1.0.0");
+        let lib_bottle = create_versioned_bottle_tarball("lib", "1.0.0");
+        let extra_bottle = create_versioned_bottle_tarball("extra", "1.0.0");
+        mount_formula(&mock_server, "app", "1.0.0", &["lib"], &app_bottle).await;
+        mount_formula(&mock_server, "lib", "1.0.0", &[], &lib_bottle).await;
+        mount_formula(&mock_server, "extra", "1.0.0", &[], &extra_bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer.api_client =
+            ApiClient::with_base_url(mock_server.uri()).with_cache(ApiCache::in_memory().unwrap());
+
+        installer.install(&["app".to_string()], true).await.unwrap();
+        installer.uninstall("app", true).unwrap();
+        installer.uninstall("lib", true).unwrap();
+        // Metadata for extra is cached, its bottle never is
+        installer.plan(&["extra".to_string()]).await.unwrap();
+
+        mock_server.reset().await;
+        installer.set_offline(true);
+
+        installer.install(&["app".to_string()], true).await.unwrap();
+        assert!(installer.is_installed("app"));
+        assert!(installer.is_installed("lib"));
+
+        match installer
+            .install(&["extra".to_string(), "ghost".to_string()], true)
+            .await
+        {
+            Err(Error::OfflineUnavailable { missing }) => {
+                assert_eq!(missing, vec!["formula ghost"])
+            }
+            _ => panic!("expected an offline error"),
+        }
+
+        match installer.install(&["extra".to_string()], true).await {
+            Err(Error::OfflineUnavailable { missing }) => {
+                assert_eq!(missing.len(), 1);
+                assert!(missing[0].starts_with("bottle extra"), "{missing:?}");
+            }
+            _ => panic!("expected an offline error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn exported_cache_installs_offline_on_another_machine() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+        let other = TempDir::new().unwrap();
+
+        let app_bottle = create_versioned_bottle_tarball("app", "1.0.0");
+        let lib_bottle = create_versioned_bottle_tarball("lib", "1.0.0");
+        mount_formula(&mock_server, "app", "1.0.0", &["lib"], &app_bottle).await;
+        mount_formula(&mock_server, "lib", "1.0.0", &[], &lib_bottle).await;
+
+        let installer = test_installer(&tmp, &mock_server);
+        let plan = installer.plan(&["app".to_string()]).await.unwrap();
+        let archive_path = tmp.path().join("cache.tar");
+        let exported = installer.export_cache(&plan, &archive_path).await.unwrap();
+        assert_eq!(exported.manifest.bottles.len(), 2);
+        assert!(!installer.is_installed("app"));
+
+        mock_server.reset().await;
+
+        let mut air_gapped = test_installer(&other, &mock_server);
+        air_gapped.api_client =
+            ApiClient::with_base_url(mock_server.uri()).with_cache(ApiCache::in_memory().unwrap());
+        air_gapped.set_offline(true);
+
+        let (imported, added) = air_gapped.import_cache(&archive_path).unwrap();
+        assert_eq!(imported.manifest, exported.manifest);
+        assert_eq!(added, 2);
+
+        air_gapped
+            .install(&["app".to_string()], true)
+            .await
+            .unwrap();
+        assert!(air_gapped.is_installed("app"));
+        assert!(air_gapped.is_installed("lib"));
+    }
+
+    #[tokio::test]
+    async fn search_marks_installed_formulas() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let bottle = create_bottle_tarball("jq");
+        mount_formula(&mock_server, "jq", "1.7.1", &[], &bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer.install(&["jq".to_string()], true).await.unwrap();
+
+        let entry = |name: &str, desc: &str| {
+            serde_json::json!({
+                "name": name,
+                "desc": desc,
+                "versions": { "stable": "1.7.1" },
+                "dependencies": [],
+                "bottle": { "stable": { "files": {} } }
+            })
+        };
+        let index = serde_json::json!([
+            entry("jq", "Lightweight and flexible command-line JSON processor"),
+            entry("jo", "JSON output from a shell"),
+            entry("wget", "Internet file retriever"),
+        ]);
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(index.to_string()))
+            .mount(&mock_server)
+            .await;
+        installer.api_client = ApiClient::with_base_url(format!("{}/formula", mock_server.uri()));
+
+        let results = installer.search("json", true).await.unwrap();
+        let found: Vec<(&str, bool)> = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.installed))
+            .collect();
+        assert_eq!(found, vec![("jo", false), ("jq", true)]);
+
+        let results = installer.search("wget", false).await.unwrap();
+        assert_eq!(results[0].desc.as_deref(), Some("Internet file retriever"));
+    }
+
+    #[tokio::test]
+    async fn installs_aliases_under_canonical_name() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let bottle = create_versioned_bottle_tarball("python@3.13", "3.13.1");
+        mount_formula(&mock_server, "python@3.13", "3.13.1", &[], &bottle).await;
+
+        let mut formula: serde_json::Value = serde_json::from_str(&formula_json(
+            &mock_server.uri(),
+            "python@3.13",
+            "3.13.1",
+            &[],
+            &sha256_hex(&bottle),
+        ))
+        .unwrap();
+        formula["aliases"] = serde_json::json!(["python", "python3"]);
+        formula["oldnames"] = serde_json::json!(["python@3"]);
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("[{formula}]")))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/formula/python@3.13.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(formula.to_string()))
+            .mount(&mock_server)
+            .await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer.api_client = ApiClient::with_base_url(format!("{}/formula", mock_server.uri()))
+            .with_formula_index(true);
+
+        let plan = installer.plan(&["python".to_string()]).await.unwrap();
+        assert_eq!(plan.formulas.len(), 1);
+        assert_eq!(plan.canonical_name("python"), "python@3.13");
+        assert!(plan.requested.contains("python@3.13"));
+        installer.execute(plan, true).await.unwrap();
+
+        assert!(installer.is_installed("python@3.13"));
+        assert!(!installer.is_installed("python"));
+        assert_eq!(
+            installer.resolve_installed_name("python@3").as_deref(),
+            Some("python@3.13")
+        );
+
+        // Another alias lands on the same keg
+        installer
+            .install(&["python3".to_string()], true)
+            .await
+            .unwrap();
+        assert_eq!(installer.list_installed().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn installs_formulas_from_a_local_tap() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+        let tap_dir = tmp.path().join("acme-tap");
+        fs::create_dir_all(&tap_dir).unwrap();
+
+        let tool_bottle = create_versioned_bottle_tarball("deployctl", "2.0.0");
+        let lib_bottle = create_versioned_bottle_tarball("libdeploy", "1.0.0");
+        mount_formula(&mock_server, "deployctl", "2.0.0", &[], &tool_bottle).await;
+        mount_formula(&mock_server, "libdeploy", "1.0.0", &[], &lib_bottle).await;
+        fs::write(
+            tap_dir.join("deployctl.json"),
+            formula_json(
+                &mock_server.uri(),
+                "deployctl",
+                "2.0.0",
+                &["acme/tools/libdeploy"],
+                &sha256_hex(&tool_bottle),
+            ),
+        )
+        .unwrap();
+        fs::write(
+            tap_dir.join("libdeploy.json"),
+            formula_json(
+                &mock_server.uri(),
+                "libdeploy",
+                "1.0.0",
+                &[],
+                &sha256_hex(&lib_bottle),
+            ),
+        )
+        .unwrap();
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        assert!(matches!(
+            installer.plan(&["acme/tools/deployctl".to_string()]).await,
+            Err(Error::UnsupportedTap { .. })
+        ));
+
+        installer
+            .add_tap("acme/tools", &tap_dir.to_string_lossy())
+            .unwrap();
+        assert!(installer.add_tap("acme", "/tmp").is_err());
+
+        let plan = installer
+            .plan(&["acme/tools/deployctl".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(plan.canonical_name("acme/tools/deployctl"), "deployctl");
+        installer.execute(plan, true).await.unwrap();
+
+        assert!(installer.is_installed("deployctl"));
+        assert!(!installer.get_installed("libdeploy").unwrap().requested);
+        assert_eq!(
+            installer.dependents("libdeploy").unwrap(),
+            vec!["deployctl"]
+        );
+        assert!(tmp.path().join("homebrew/bin/deployctl").exists());
+
+        installer.remove_tap("acme/tools").unwrap();
+        assert!(installer.list_taps().unwrap().is_empty());
+        assert!(installer.remove_tap("acme/tools").is_err());
+    }
+
+    #[tokio::test]
+    async fn tap_kegs_stay_with_their_tap_when_core_shares_the_name() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+        let tap_dir = tmp.path().join("acme-tap");
+        fs::create_dir_all(&tap_dir).unwrap();
+
+        // homebrew/core serves an unrelated, newer formula under the same name
+        let core_bottle = create_versioned_bottle_tarball("deployctl", "9.0.0");
+        mount_formula(&mock_server, "deployctl", "9.0.0", &[], &core_bottle).await;
+
+        let tap_bottle = create_versioned_bottle_tarball("deployctl", "2.0.0");
+        let newer_tap_bottle = create_versioned_bottle_tarball("deployctl", "2.1.0");
+        for (version, bottle) in [("2.0.0", &tap_bottle), ("2.1.0", &newer_tap_bottle)] {
+            Mock::given(method("GET"))
+                .and(path(format!(
+                    "/bottles/deployctl-{version}.{}.bottle.tar.gz",
+                    get_test_bottle_tag()
+                )))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(bottle.clone()))
+                .mount(&mock_server)
+                .await;
+        }
+        let write_tap_formula = |version: &str, bottle: &[u8]| {
+            fs::write(
+                tap_dir.join("deployctl.json"),
+                formula_json(
+                    &mock_server.uri(),
+                    "deployctl",
+                    version,
+                    &[],
+                    &sha256_hex(bottle),
+                ),
+            )
+            .unwrap();
+        };
+        write_tap_formula("2.0.0", &tap_bottle);
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer
+            .add_tap("acme/tools", &tap_dir.to_string_lossy())
+            .unwrap();
+        installer
+            .install(&["acme/tools/deployctl".to_string()], true)
+            .await
+            .unwrap();
+
+        let keg = installer.get_installed("deployctl").unwrap();
+        assert_eq!(keg.tap.as_deref(), Some("acme/tools"));
+        assert_eq!(keg.formula_name(), "acme/tools/deployctl");
+        assert!(installer.outdated(&[]).await.unwrap().is_empty());
+
+        write_tap_formula("2.1.0", &newer_tap_bottle);
+        let outdated = installer
+            .outdated(&["acme/tools/deployctl".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            outdated,
+            vec![OutdatedPackage {
+                name: "acme/tools/deployctl".to_string(),
+                installed_version: "2.0.0".to_string(),
+                current_version: "2.1.0".to_string(),
+            }]
+        );
+
+        // Upgrading by the reported name stays with the tap
+        let plan = installer.plan(&[outdated[0].name.clone()]).await.unwrap();
+        installer.execute(plan, true).await.unwrap();
+        let keg = installer.get_installed("deployctl").unwrap();
+        assert_eq!(keg.version, "2.1.0");
+        assert_eq!(keg.store_key, sha256_hex(&newer_tap_bottle));
+        assert_eq!(keg.tap.as_deref(), Some("acme/tools"));
+    }
+
+    #[tokio::test]
+    async fn fetched_bottles_reach_the_remote_cache_before_finishing() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let bottle = create_bottle_tarball("warmme");
+        mount_formula(&mock_server, "warmme", "1.0.0", &[], &bottle).await;
+        Mock::given(method("GET"))
+            .and(path(format!("/cache/{}", sha256_hex(&bottle))))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path(format!("/cache/{}", sha256_hex(&bottle))))
+            .respond_with(
+                ResponseTemplate::new(201).set_delay(std::time::Duration::from_millis(300)),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer.set_remote_cache(Some(format!("{}/cache", mock_server.uri())));
+        let plan = installer.plan(&["warmme".to_string()]).await.unwrap();
+        installer.fetch_bottles(&plan, None).await.unwrap();
+        installer.finish_uploads().await;
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let upload = requests
+            .iter()
+            .find(|request| request.method.as_str() == "PUT")
+            .expect("bottle was not uploaded");
+        assert_eq!(upload.body, bottle);
+    }
+
+    #[tokio::test]
+    async fn fetches_bottles_for_another_target() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let mac_bottle = create_versioned_bottle_tarball("tool", "1.0.0");
+        let linux_bottle = create_versioned_bottle_tarball("tool", "1.0.1");
+        let formula = serde_json::json!({
+            "name": "tool",
+            "versions": { "stable": "1.0.0" },
+            "dependencies": [],
+            "bottle": { "stable": { "files": {
+                "arm64_sonoma": {
+                    "url": format!("{}/bottles/tool.arm64_sonoma.tar.gz", mock_server.uri()),
+                    "sha256": sha256_hex(&mac_bottle)
+                },
+                "x86_64_linux": {
+                    "url": format!("{}/bottles/tool.x86_64_linux.tar.gz", mock_server.uri()),
+                    "sha256": sha256_hex(&linux_bottle)
+                }
+            } } }
+        });
+        Mock::given(method("GET"))
+            .and(path("/tool.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(formula.to_string()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bottles/tool.arm64_sonoma.tar.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(mac_bottle.clone()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let installer = test_installer(&tmp, &mock_server);
+        let target: zb_core::BottleTarget = "aarch64-apple-darwin".parse().unwrap();
+        let plan = installer
+            .plan_for(&["tool".to_string()], &BottleSelector::Target(target))
+            .await
+            .unwrap();
+        assert_eq!(plan.bottles[0].tag, "arm64_sonoma");
+
+        let paths = installer.fetch_bottles(&plan, None).await.unwrap();
+        assert_eq!(fs::read(&paths[0]).unwrap(), mac_bottle);
+        assert!(installer.downloader.has_blob(&sha256_hex(&mac_bottle)));
+        assert!(!installer.downloader.has_blob(&sha256_hex(&linux_bottle)));
+        assert!(!installer.is_installed("tool"));
+
+        let missing = BottleSelector::Tag("arm64_tahoe".to_string());
+        assert!(matches!(
+            installer.plan_for(&["tool".to_string()], &missing).await,
+            Err(Error::MissingFormula { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetch_unpacks_into_store_without_kegs_or_links() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let lib_bottle = create_bottle_tarball("fetchlib");
+        let app_bottle = create_bottle_tarball("fetchapp");
+        mount_formula(&mock_server, "fetchlib", "1.0.0", &[], &lib_bottle).await;
+        mount_formula(
+            &mock_server,
+            "fetchapp",
+            "1.0.0",
+            &["fetchlib"],
+            &app_bottle,
+        )
+        .await;
+
+        let installer = test_installer(&tmp, &mock_server);
+        let plan = installer.plan(&["fetchapp".to_string()]).await.unwrap();
+        let blobs = installer.fetch_bottles(&plan, None).await.unwrap();
+        assert_eq!(blobs.len(), 2);
+
+        let entries = installer.unpack_bottles(&plan, &blobs).await.unwrap();
+        for (entry, bottle) in entries.iter().zip(&plan.bottles) {
+            assert!(entry.exists());
+            assert!(installer.store.has_entry(&bottle.sha256));
+        }
+
+        assert!(installer.list_installed().unwrap().is_empty());
+        assert!(!tmp.path().join("zerobrew/cellar/fetchapp").exists());
+        assert!(!tmp.path().join("homebrew/bin/fetchapp").exists());
+    }
+
+    #[tokio::test]
+    async fn installs_a_local_bottle_without_the_api() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let bottle = create_bottle_with_receipt("localtool", "2.0.0_1", &[]);
+        let bottle_path = tmp.path().join(format!(
+            "localtool--2.0.0_1.{}.bottle.tar.gz",
+            get_test_bottle_tag()
+        ));
+        fs::write(&bottle_path, &bottle).unwrap();
+
+        let mut installer = test_installer(&tmp, &mock_server);
+
+        // A plan that fails leaves nothing behind in the caches
+        let foreign_tag = if cfg!(target_os = "linux") {
+            "arm64_sonoma"
+        } else {
+            "x86_64_linux"
+        };
+        let foreign_path = tmp
+            .path()
+            .join(format!("localtool--2.0.0_1.{foreign_tag}.bottle.tar.gz"));
+        fs::write(&foreign_path, &bottle).unwrap();
+        let foreign = installer.local_bottle_formula(&foreign_path).unwrap();
+        assert!(matches!(
+            installer.plan_with_formulas(&[], vec![foreign]).await,
+            Err(Error::UnsupportedBottle { .. })
+        ));
+        assert!(!installer.downloader.has_blob(&sha256_hex(&bottle)));
+        assert!(!installer.store.has_entry(&sha256_hex(&bottle)));
+
+        let formula = installer.local_bottle_formula(&bottle_path).unwrap();
+        assert_eq!(formula.name, "localtool");
+        assert_eq!(formula.effective_version(), "2.0.0_1");
+        assert!(formula.dependencies.is_empty());
+
+        let plan = installer
+            .plan_with_formulas(&[], vec![formula])
+            .await
+            .unwrap();
+        assert_eq!(plan.bottles[0].tag, get_test_bottle_tag());
+        installer.execute(plan, true).await.unwrap();
+
+        assert!(installer.is_installed("localtool"));
+        assert!(
+            tmp.path()
+                .join("zerobrew/cellar/localtool/2.0.0_1")
+                .exists()
+        );
+        assert!(tmp.path().join("homebrew/bin/localtool").exists());
+    }
+
+    #[tokio::test]
+    async fn local_bottles_install_their_receipt_dependencies() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        mount_formula(
+            &mock_server,
+            "libfoo",
+            "1.0.0",
+            &[],
+            &create_bottle_tarball("libfoo"),
+        )
+        .await;
+
+        let bottle_path = tmp.path().join("foo.bottle.tar.gz");
+        fs::write(
+            &bottle_path,
+            create_bottle_with_receipt("foo", "3.1.0", &["libfoo"]),
+        )
+        .unwrap();
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        let formula = installer.local_bottle_formula(&bottle_path).unwrap();
+        assert_eq!(formula.dependencies, vec!["libfoo"]);
+
+        let plan = installer
+            .plan_with_formulas(&[], vec![formula])
+            .await
+            .unwrap();
+        installer.execute(plan, true).await.unwrap();
+        assert!(installer.is_installed("foo"));
+        assert!(installer.is_installed("libfoo"));
+
+        // Without a receipt the dependencies are unknown, so refuse to guess
+        let bare_path = tmp.path().join("bare.bottle.tar.gz");
+        fs::write(&bare_path, create_bottle_tarball("bare")).unwrap();
+        match installer.local_bottle_formula(&bare_path) {
+            Err(Error::FileError { message }) => {
+                assert!(message.contains("INSTALL_RECEIPT.json"), "{message}")
+            }
+            other => panic!("expected a missing receipt error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn installs_a_formula_json_file_with_api_dependencies() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let lib_bottle = create_versioned_bottle_tarball("lib", "1.0.0");
+        let app_bottle = create_versioned_bottle_tarball("app", "1.0.0");
+        mount_formula(&mock_server, "lib", "1.0.0", &[], &lib_bottle).await;
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/bottles/app-1.0.0.{}.bottle.tar.gz",
+                get_test_bottle_tag()
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(app_bottle.clone()))
+            .mount(&mock_server)
+            .await;
+
+        let json = formula_json(
+            &mock_server.uri(),
+            "app",
+            "1.0.0",
+            &["lib"],
+            &sha256_hex(&app_bottle),
+        );
+        let formula: Formula = serde_json::from_str(&json).unwrap();
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        let plan = installer
+            .plan_with_formulas(&[], vec![formula])
+            .await
+            .unwrap();
+        let names: Vec<&str> = plan.formulas.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["lib", "app"]);
+        assert!(plan.requested.contains("app"));
+        assert!(!plan.requested.contains("lib"));
+
+        installer.execute(plan, true).await.unwrap();
+        assert!(installer.is_installed("app"));
+        assert!(installer.is_installed("lib"));
+    }
+
+    #[tokio::test]
+    async fn downloads_homebrew_bottles_from_the_bottle_domain() {
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let bottle = create_bottle_tarball("mirrored");
+        let json = formula_json(
+            zb_core::DEFAULT_BOTTLE_DOMAIN,
+            "mirrored",
+            "1.0.0",
+            &[],
+            &sha256_hex(&bottle),
+        );
+        Mock::given(method("GET"))
+            .and(path("/mirrored.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(json))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/homebrew-bottles/mirrored-1.0.0.{}.bottle.tar.gz",
+                get_test_bottle_tag()
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(bottle))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer.set_bottle_domain(Some(format!("{}/homebrew-bottles", mock_server.uri())));
+        let plan = installer.plan(&["mirrored".to_string()]).await.unwrap();
+        // Lockfiles and plans keep the canonical URL; only the download moves
+        assert!(
+            plan.bottles[0]
+                .url
+                .starts_with(zb_core::DEFAULT_BOTTLE_DOMAIN)
+        );
+
+        installer.execute(plan, true).await.unwrap();
+        assert!(installer.is_installed("mirrored"));
+    }
+
+    #[tokio::test]
+    async fn bottles_an_installed_keg_with_placeholders_restored() {
+        use flate2::Compression;
+        use flate2::read::GzDecoder;
+        use flate2::write::GzEncoder;
+        use std::io::{Read, Write};
+
+        let mock_server = MockServer::start().await;
+        let tmp = TempDir::new().unwrap();
+
+        let script = "#!/bin/sh\nexec @@HOMEBREW_PREFIX@@/opt/patched/libexec/patched\n";
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_path("patched/1.0.0/bin/patched").unwrap();
+        header.set_size(script.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append(&header, script.as_bytes()).unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&builder.into_inner().unwrap()).unwrap();
+        let bottle = encoder.finish().unwrap();
+
+        mount_formula(
+            &mock_server,
+            "dep",
+            "1.0.0",
+            &[],
+            &create_bottle_tarball("dep"),
+        )
+        .await;
+        mount_formula(&mock_server, "patched", "1.0.0", &["dep"], &bottle).await;
+
+        let mut installer = test_installer(&tmp, &mock_server);
+        installer
+            .install(&["patched".to_string()], true)
+            .await
+            .unwrap();
+        let keg_script = tmp.path().join("zerobrew/cellar/patched/1.0.0/bin/patched");
+        assert!(!fs::read_to_string(&keg_script).unwrap().contains("@@"));
+
+        let out = tmp.path().join("bottles");
+        let built = installer.bottle("patched", &out, None, None).unwrap();
+        assert_eq!(
+            built.path,
+            out.join(format!(
+                "patched--1.0.0.{}.bottle.tar.gz",
+                get_test_bottle_tag()
+            ))
+        );
+        assert_eq!(sha256_hex(&fs::read(&built.path).unwrap()), built.sha256);
+        assert!(!fs::read_to_string(&keg_script).unwrap().contains("@@"));
+
+        let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(&built.path).unwrap()));
+        let mut bottled_script = None;
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            if entry.path().unwrap() == Path::new("patched/1.0.0/bin/patched") {
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents).unwrap();
+                bottled_script = Some(contents);
+            }
+        }
+        assert_eq!(bottled_script.as_deref(), Some(script));
+
+        let fragment: Formula =
+            serde_json::from_str(&fs::read_to_string(&built.json_path).unwrap()).unwrap();
+        assert_eq!(fragment, built.formula);
+        assert_eq!(fragment.dependencies, vec!["dep"]);
+        assert_eq!(select_bottle(&fragment).unwrap().sha256, built.sha256);
+
+        // The fragment installs the bottle elsewhere straight from its file:// URL
+        let other = TempDir::new().unwrap();
+        let mut elsewhere = test_installer(&other, &mock_server);
+        let plan = elsewhere
+            .plan_with_formulas(&[], vec![fragment])
+            .await
+            .unwrap();
+        elsewhere.execute(plan, true).await.unwrap();
+        let installed = fs::read_to_string(
+            other
+                .path()
+                .join("zerobrew/cellar/patched/1.0.0/bin/patched"),
+        )
+        .unwrap();
+        assert!(installed.contains(&other.path().join("zerobrew").display().to_string()));
+
+        assert!(matches!(
+            installer.bottle("ghost", &out, None, None),
+            Err(Error::NotInstalled { .. })
+        ));
+        assert!(matches!(
+            installer.bottle("patched", &out, None, Some("../sonoma")),
+            Err(Error::InvalidArgument { .. })
+        ));
+        let tagged = installer
+            .bottle("patched", &out, None, Some("arm64_sonoma"))
+            .unwrap();
+        assert_eq!(tagged.tag, "arm64_sonoma");
+        assert!(
+            tagged
+                .path
+                .ends_with("patched--1.0.0.arm64_sonoma.bottle.tar.gz")
+        );
+    }
+
+    #[test]
+    fn reads_tags_from_bottle_file_names() {
+        assert_eq!(
+            bottle_tag_from_file_name("jq--1.7.1.arm64_sonoma.bottle.tar.gz").as_deref(),
+            Some("arm64_sonoma")
+        );
+        assert_eq!(
+            bottle_tag_from_file_name("git--2.45.0.x86_64_linux.bottle.1.tar.gz").as_deref(),
+            Some("x86_64_linux")
+        );
+        assert_eq!(bottle_tag_from_file_name("jq.tar.gz"), None);
+        assert_eq!(
+            bottle_tag_from_file_name("jq--1.7.1.all.bottlex.tar.gz"),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn uninstall_cleans_everything() {
         let mock_server = MockServer::start().await;
@@ -726,7 +2798,7 @@ mod tests {
         assert!(prefix.join("bin/uninstallme").exists());
 
         // Uninstall
-        installer.uninstall("uninstallme").unwrap();
+        installer.uninstall("uninstallme", false).unwrap();
 
         // Verify everything cleaned up
         assert!(!installer.is_installed("uninstallme"));
@@ -803,7 +2875,7 @@ mod tests {
         // Store entry should exist before GC
         assert!(root.join("store").join(&bottle_sha).exists());
 
-        installer.uninstall("gctest").unwrap();
+        installer.uninstall("gctest", false).unwrap();
 
         // Store entry should still exist (refcount decremented but not GC'd)
         assert!(root.join("store").join(&bottle_sha).exists());
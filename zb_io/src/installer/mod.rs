@@ -5,4 +5,7 @@ pub use homebrew::{
     HomebrewMigrationPackages, HomebrewPackage, categorize_packages, get_homebrew_packages,
     parse_casks_from_plain_text, parse_formulas_from_json,
 };
-pub use install::{ExecuteResult, InstallPlan, Installer, create_installer};
+pub use install::{
+    BuiltBottle, ExecuteResult, InstallPlan, Installer, OutdatedPackage, SearchResult,
+    create_installer,
+};
@@ -11,12 +11,15 @@ pub mod storage;
 pub use cellar::{Cellar, LinkedFile, Linker};
 pub use extraction::extract_tarball;
 pub use installer::{
-    ExecuteResult, HomebrewMigrationPackages, HomebrewPackage, InstallPlan, Installer,
-    create_installer, get_homebrew_packages,
+    BuiltBottle, ExecuteResult, HomebrewMigrationPackages, HomebrewPackage, InstallPlan, Installer,
+    OutdatedPackage, SearchResult, create_installer, get_homebrew_packages,
 };
 pub use network::{
-    ApiCache, ApiClient, DownloadProgressCallback, DownloadRequest, Downloader, ParallelDownloader,
+    ApiCache, ApiClient, DownloadProgressCallback, DownloadRequest, Downloader, MirrorServer,
+    ParallelDownloader,
 };
 pub use progress::{InstallProgress, ProgressCallback};
 pub use ssl::{find_ca_bundle_from_prefix, find_ca_dir};
-pub use storage::{BlobCache, Database, InstalledKeg, Store};
+pub use storage::{
+    ArchivedBottle, BlobCache, CacheArchive, CacheManifest, Database, InstalledKeg, Store,
+};
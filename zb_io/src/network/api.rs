@@ -1,15 +1,45 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use tokio::sync::OnceCell;
+
 use crate::network::cache::{ApiCache, CacheEntry};
-use zb_core::{Error, Formula};
+use zb_core::{Error, Formula, split_tap_name};
+
+/// Homebrew's API root; the formula endpoints live under `{domain}/formula`
+const DEFAULT_API_DOMAIN: &str = "https://formulae.brew.sh/api";
+
+/// A domain override from the environment, preferring zerobrew's own variable
+/// over Homebrew's so both can be set differently. Empty values count as unset.
+pub(crate) fn domain_from_env(zerobrew_var: &str, homebrew_var: &str) -> Option<String> {
+    [zerobrew_var, homebrew_var]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|value| value.trim().trim_end_matches('/').to_string())
+        .find(|value| !value.is_empty())
+}
 
 pub struct ApiClient {
     base_url: String,
     client: reqwest::Client,
     cache: Option<ApiCache>,
+    /// Resolve formulas from the bulk index instead of one request per formula
+    use_index: bool,
+    index: OnceCell<BTreeMap<String, Formula>>,
+    /// Serve cached responses without revalidating and never touch the network
+    offline: bool,
+    /// Third-party taps (`user/repo`), each a local directory or HTTP base URL
+    /// serving `{formula}.json` in the same schema as the formula API
+    taps: BTreeMap<String, String>,
 }
 
 impl ApiClient {
+    /// A client for the formula API at `ZEROBREW_API_DOMAIN` or `HOMEBREW_API_DOMAIN`,
+    /// falling back to formulae.brew.sh
     pub fn new() -> Self {
-        Self::with_base_url("https://formulae.brew.sh/api/formula".to_string())
+        let domain = domain_from_env("ZEROBREW_API_DOMAIN", "HOMEBREW_API_DOMAIN")
+            .unwrap_or_else(|| DEFAULT_API_DOMAIN.to_string());
+        Self::with_base_url(format!("{domain}/formula"))
     }
 
     pub fn with_base_url(base_url: String) -> Self {
@@ -24,6 +54,10 @@ impl ApiClient {
             base_url,
             client,
             cache: None,
+            use_index: false,
+            index: OnceCell::new(),
+            offline: false,
+            taps: BTreeMap::new(),
         }
     }
 
@@ -32,12 +66,209 @@ impl ApiClient {
         self
     }
 
+    pub fn with_formula_index(mut self, enabled: bool) -> Self {
+        self.use_index = enabled;
+        self
+    }
+
+    pub fn set_formula_index(&mut self, enabled: bool) {
+        self.use_index = enabled;
+    }
+
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    pub fn set_taps(&mut self, taps: BTreeMap<String, String>) {
+        self.taps = taps;
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// The bulk index lives next to the per-formula endpoints, e.g. `.../api/formula.json`
+    fn index_url(&self) -> String {
+        format!("{}.json", self.base_url.trim_end_matches('/'))
+    }
+
+    /// Fetch a formula by name, resolving Homebrew aliases and old names to the
+    /// canonical formula. The returned formula always carries its canonical name.
     pub async fn get_formula(&self, name: &str) -> Result<Formula, Error> {
+        if let Some((tap, formula)) = split_tap_name(name) {
+            return self.fetch_tap_formula(name, tap, formula).await;
+        }
+
+        match self.fetch_formula(name).await {
+            Err(err @ (Error::MissingFormula { .. } | Error::OfflineUnavailable { .. })) => {
+                match self.resolve_alias(name).await {
+                    Some(canonical) => self.fetch_formula(&canonical).await,
+                    None => Err(err),
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Look up the canonical name behind an alias or old name in the formula index.
+    /// Per-formula endpoints only exist for canonical names, so this fetches and
+    /// caches the index on first use; later lookups revalidate the cached copy.
+    pub async fn resolve_alias(&self, name: &str) -> Option<String> {
+        let index = self.formula_index().await.ok()?;
+        index
+            .values()
+            .find(|formula| formula.is_known_as(name))
+            .map(|formula| formula.name.clone())
+    }
+
+    async fn fetch_formula(&self, name: &str) -> Result<Formula, Error> {
+        // Formulas newer than the index still resolve through their own endpoint
+        if self.use_index {
+            match self.formula_index().await {
+                Ok(index) => {
+                    if let Some(formula) = index.get(name) {
+                        return Ok(formula.clone());
+                    }
+                }
+                // Offline, an uncached index just means falling back to per-formula entries
+                Err(_) if self.offline => {}
+                Err(e) => return Err(e),
+            }
+        }
+
         let url = format!("{}/{}.json", self.base_url, name);
+        let body = if self.offline {
+            self.cached_body(&url)
+                .ok_or_else(|| Error::OfflineUnavailable {
+                    missing: vec![format!("formula {name}")],
+                })?
+        } else {
+            self.fetch(&url)
+                .await?
+                .ok_or_else(|| Error::MissingFormula {
+                    name: name.to_string(),
+                })?
+        };
+
+        let formula: Formula = serde_json::from_str(&body).map_err(|e| Error::NetworkFailure {
+            message: format!("failed to parse formula JSON: {e}"),
+        })?;
+
+        Ok(formula)
+    }
 
-        let cached_entry = self.cache.as_ref().and_then(|c| c.get(&url));
+    /// Fetch `acme/tools/deployctl` from the `acme/tools` tap
+    async fn fetch_tap_formula(
+        &self,
+        qualified: &str,
+        tap: &str,
+        formula: &str,
+    ) -> Result<Formula, Error> {
+        let source = self.taps.get(tap).ok_or_else(|| Error::UnsupportedTap {
+            name: qualified.to_string(),
+        })?;
 
-        let mut request = self.client.get(&url);
+        let body = if is_url(source) {
+            let url = format!("{}/{formula}.json", source.trim_end_matches('/'));
+            if self.offline {
+                self.cached_body(&url)
+                    .ok_or_else(|| Error::OfflineUnavailable {
+                        missing: vec![format!("formula {qualified}")],
+                    })?
+            } else {
+                self.fetch(&url)
+                    .await?
+                    .ok_or_else(|| Error::MissingFormula {
+                        name: qualified.to_string(),
+                    })?
+            }
+        } else {
+            let path =
+                Path::new(source.trim_start_matches("file://")).join(format!("{formula}.json"));
+            match std::fs::read_to_string(&path) {
+                Ok(body) => body,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(Error::MissingFormula {
+                        name: qualified.to_string(),
+                    });
+                }
+                Err(e) => {
+                    return Err(Error::FileError {
+                        message: format!("failed to read {}: {e}", path.display()),
+                    });
+                }
+            }
+        };
+
+        serde_json::from_str(&body).map_err(|e| Error::NetworkFailure {
+            message: format!("failed to parse formula JSON for {qualified}: {e}"),
+        })
+    }
+
+    /// Download the bulk formula index once per client, revalidating any cached copy
+    pub async fn formula_index(&self) -> Result<&BTreeMap<String, Formula>, Error> {
+        self.index
+            .get_or_try_init(|| async {
+                let url = self.index_url();
+                let body = if self.offline {
+                    self.cached_body(&url)
+                        .ok_or_else(|| Error::OfflineUnavailable {
+                            missing: vec!["formula index".to_string()],
+                        })?
+                } else {
+                    self.fetch(&url)
+                        .await?
+                        .ok_or_else(|| Error::NetworkFailure {
+                            message: format!("formula index not found at {url}"),
+                        })?
+                };
+                parse_formula_index(&body)
+            })
+            .await
+    }
+
+    /// Store formula JSON in the API cache under the URL `get_formula` requests for
+    /// `name`, so it resolves offline. Returns `false` when there is no cache or the
+    /// name comes from a local tap, which is read from disk anyway.
+    pub fn seed_formula(&self, name: &str, body: &str) -> Result<bool, Error> {
+        let Some(cache) = &self.cache else {
+            return Ok(false);
+        };
+
+        let url = match split_tap_name(name) {
+            Some((tap, formula)) => match self.taps.get(tap) {
+                Some(source) if is_url(source) => {
+                    format!("{}/{formula}.json", source.trim_end_matches('/'))
+                }
+                _ => return Ok(false),
+            },
+            None => format!("{}/{}.json", self.base_url, name),
+        };
+
+        let entry = CacheEntry {
+            etag: None,
+            last_modified: None,
+            body: body.to_string(),
+        };
+        cache.put(&url, &entry).map_err(|e| Error::FileError {
+            message: format!("failed to cache formula {name}: {e}"),
+        })?;
+        Ok(true)
+    }
+
+    fn cached_body(&self, url: &str) -> Option<String> {
+        self.cache
+            .as_ref()
+            .and_then(|c| c.get(url))
+            .map(|entry| entry.body)
+    }
+
+    /// GET a URL, sending cached validators and serving the cached body on 304.
+    /// Returns `None` when the server answers 404.
+    async fn fetch(&self, url: &str) -> Result<Option<String>, Error> {
+        let cached_entry = self.cache.as_ref().and_then(|c| c.get(url));
+
+        let mut request = self.client.get(url);
 
         if let Some(ref entry) = cached_entry {
             if let Some(ref etag) = entry.etag {
@@ -55,17 +286,11 @@ impl ApiClient {
         if response.status() == reqwest::StatusCode::NOT_MODIFIED
             && let Some(entry) = cached_entry
         {
-            let formula: Formula =
-                serde_json::from_str(&entry.body).map_err(|e| Error::NetworkFailure {
-                    message: format!("failed to parse cached formula JSON: {e}"),
-                })?;
-            return Ok(formula);
+            return Ok(Some(entry.body));
         }
 
         if response.status() == reqwest::StatusCode::NOT_FOUND {
-            return Err(Error::MissingFormula {
-                name: name.to_string(),
-            });
+            return Ok(None);
         }
 
         if !response.status().is_success() {
@@ -96,15 +321,29 @@ impl ApiClient {
                 last_modified,
                 body: body.clone(),
             };
-            let _ = cache.put(&url, &entry);
+            let _ = cache.put(url, &entry);
         }
 
-        let formula: Formula = serde_json::from_str(&body).map_err(|e| Error::NetworkFailure {
-            message: format!("failed to parse formula JSON: {e}"),
+        Ok(Some(body))
+    }
+}
+
+pub(crate) fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// Parse the bulk index, skipping entries zerobrew can't use (e.g. formulas without bottles)
+fn parse_formula_index(body: &str) -> Result<BTreeMap<String, Formula>, Error> {
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_str(body).map_err(|e| Error::NetworkFailure {
+            message: format!("failed to parse formula index: {e}"),
         })?;
 
-        Ok(formula)
-    }
+    Ok(entries
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value::<Formula>(entry).ok())
+        .map(|formula| (formula.name.clone(), formula))
+        .collect())
 }
 
 impl Default for ApiClient {
@@ -262,4 +501,273 @@ mod tests {
         assert_eq!(formula.name, "foo");
         assert_eq!(formula.versions.stable, "1.2.3");
     }
+
+    fn index_body(fixtures: &[&str]) -> String {
+        let entries: Vec<serde_json::Value> = fixtures
+            .iter()
+            .map(|f| serde_json::from_str(f).unwrap())
+            .collect();
+        serde_json::to_string(&entries).unwrap()
+    }
+
+    #[tokio::test]
+    async fn resolves_formulas_from_bulk_index() {
+        let mock_server = MockServer::start().await;
+        let index = index_body(&[
+            include_str!("../../../zb_core/fixtures/formula_foo.json"),
+            include_str!("../../../zb_core/fixtures/formula_bar.json"),
+            r#"{"name": "nobottle", "versions": {"stable": "1.0"}, "dependencies": [], "bottle": {}}"#,
+        ]);
+
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(index))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = ApiClient::with_base_url(format!("{}/formula", mock_server.uri()))
+            .with_formula_index(true);
+
+        assert_eq!(client.get_formula("foo").await.unwrap().name, "foo");
+        assert_eq!(client.get_formula("bar").await.unwrap().name, "bar");
+        assert_eq!(client.formula_index().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_formula_endpoint_when_missing_from_index() {
+        let mock_server = MockServer::start().await;
+        let fixture = include_str!("../../../zb_core/fixtures/formula_foo.json");
+
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/formula/foo.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(fixture))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = ApiClient::with_base_url(format!("{}/formula", mock_server.uri()))
+            .with_formula_index(true);
+
+        assert_eq!(client.get_formula("foo").await.unwrap().name, "foo");
+        assert!(matches!(
+            client.get_formula("nonexistent").await.unwrap_err(),
+            Error::MissingFormula { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolves_aliases_and_old_names_through_index() {
+        let mock_server = MockServer::start().await;
+        let mut formula: serde_json::Value =
+            serde_json::from_str(include_str!("../../../zb_core/fixtures/formula_foo.json"))
+                .unwrap();
+        formula["name"] = "python@3.13".into();
+        formula["aliases"] = serde_json::json!(["python", "python3"]);
+        formula["oldnames"] = serde_json::json!(["python@3"]);
+
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("[{formula}]")))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/formula/python@3.13.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(formula.to_string()))
+            .mount(&mock_server)
+            .await;
+
+        // The index was cached by an earlier run
+        let cache = ApiCache::in_memory().unwrap();
+        let entry = CacheEntry {
+            etag: None,
+            last_modified: None,
+            body: format!("[{formula}]"),
+        };
+        cache
+            .put(&format!("{}/formula.json", mock_server.uri()), &entry)
+            .unwrap();
+        let client =
+            ApiClient::with_base_url(format!("{}/formula", mock_server.uri())).with_cache(cache);
+
+        assert_eq!(
+            client.get_formula("python").await.unwrap().name,
+            "python@3.13"
+        );
+        assert_eq!(
+            client.get_formula("python@3").await.unwrap().name,
+            "python@3.13"
+        );
+        assert_eq!(
+            client.resolve_alias("python3").await.as_deref(),
+            Some("python@3.13")
+        );
+        assert!(matches!(
+            client.get_formula("ruby").await.unwrap_err(),
+            Error::MissingFormula { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn unknown_names_fetch_and_cache_the_index_to_resolve_aliases() {
+        let mock_server = MockServer::start().await;
+        let mut formula: serde_json::Value =
+            serde_json::from_str(include_str!("../../../zb_core/fixtures/formula_foo.json"))
+                .unwrap();
+        formula["name"] = "python@3.13".into();
+        formula["aliases"] = serde_json::json!(["python"]);
+
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("[{formula}]")))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/formula/python@3.13.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(formula.to_string()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/formula/python.json"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/formula/ruby.json"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        // Default settings: no index enabled and nothing cached yet
+        let index_url = format!("{}/formula.json", mock_server.uri());
+        let client = ApiClient::with_base_url(format!("{}/formula", mock_server.uri()))
+            .with_cache(ApiCache::in_memory().unwrap());
+
+        assert_eq!(
+            client.get_formula("python").await.unwrap().name,
+            "python@3.13"
+        );
+        assert!(client.cache.as_ref().unwrap().contains(&index_url));
+        assert!(matches!(
+            client.get_formula("ruby").await.unwrap_err(),
+            Error::MissingFormula { name } if name == "ruby"
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetches_formulas_from_registered_taps() {
+        let mock_server = MockServer::start().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let fixture = include_str!("../../../zb_core/fixtures/formula_foo.json");
+        std::fs::write(tmp.path().join("foo.json"), fixture).unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/tools/foo.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(fixture))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client = ApiClient::with_base_url(mock_server.uri());
+        client.set_taps(BTreeMap::from([
+            (
+                "acme/local".to_string(),
+                tmp.path().to_string_lossy().into_owned(),
+            ),
+            (
+                "acme/remote".to_string(),
+                format!("{}/tools/", mock_server.uri()),
+            ),
+        ]));
+
+        assert_eq!(
+            client.get_formula("acme/local/foo").await.unwrap().name,
+            "foo"
+        );
+        assert_eq!(
+            client.get_formula("acme/remote/foo").await.unwrap().name,
+            "foo"
+        );
+        assert!(matches!(
+            client.get_formula("acme/local/bar").await.unwrap_err(),
+            Error::MissingFormula { name } if name == "acme/local/bar"
+        ));
+        assert!(matches!(
+            client.get_formula("other/tap/foo").await.unwrap_err(),
+            Error::UnsupportedTap { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn revalidates_cached_index_with_etag() {
+        let mock_server = MockServer::start().await;
+        let tmp = tempfile::TempDir::new().unwrap();
+        let cache_path = tmp.path().join("api.sqlite3");
+        let index = index_body(&[include_str!("../../../zb_core/fixtures/formula_foo.json")]);
+        let base_url = format!("{}/formula", mock_server.uri());
+
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(index)
+                    .insert_header("etag", "\"idx1\""),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = ApiClient::with_base_url(base_url.clone())
+            .with_cache(ApiCache::open(&cache_path).unwrap())
+            .with_formula_index(true);
+        client.get_formula("foo").await.unwrap();
+
+        mock_server.reset().await;
+        Mock::given(method("GET"))
+            .and(path("/formula.json"))
+            .and(header("If-None-Match", "\"idx1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // A fresh client (a new zb invocation) revalidates instead of downloading again
+        let client = ApiClient::with_base_url(base_url)
+            .with_cache(ApiCache::open(&cache_path).unwrap())
+            .with_formula_index(true);
+        assert_eq!(client.get_formula("foo").await.unwrap().name, "foo");
+    }
+
+    #[tokio::test]
+    async fn offline_serves_cache_without_revalidating() {
+        let mock_server = MockServer::start().await;
+        let fixture = include_str!("../../../zb_core/fixtures/formula_foo.json");
+
+        Mock::given(method("GET"))
+            .and(path("/foo.json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(fixture))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut client =
+            ApiClient::with_base_url(mock_server.uri()).with_cache(ApiCache::in_memory().unwrap());
+        client.get_formula("foo").await.unwrap();
+
+        // Any further request would break the expect(1) above
+        client.set_offline(true);
+        assert_eq!(client.get_formula("foo").await.unwrap().name, "foo");
+
+        let err = client.get_formula("bar").await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::OfflineUnavailable { missing } if missing == vec!["formula bar".to_string()]
+        ));
+    }
 }
@@ -55,6 +55,16 @@ impl ApiCache {
             .ok()
     }
 
+    pub fn contains(&self, url: &str) -> bool {
+        self.conn
+            .query_row(
+                "SELECT 1 FROM api_cache WHERE url = ?1",
+                params![url],
+                |_| Ok(()),
+            )
+            .is_ok()
+    }
+
     pub fn put(&self, url: &str, entry: &CacheEntry) -> Result<(), rusqlite::Error> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -1,6 +1,6 @@
 use std::collections::{BTreeMap, HashMap};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
@@ -14,6 +14,7 @@ use reqwest::header::{
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
 use tokio::sync::{Mutex, Notify, RwLock, Semaphore, mpsc};
+use tokio::task::JoinSet;
 
 use crate::progress::InstallProgress;
 use crate::storage::blob::BlobCache;
@@ -39,6 +40,10 @@ const MAX_CONCURRENT_CHUNKS: usize = 6;
 /// Maximum retry attempts for failed chunk downloads
 const MAX_CHUNK_RETRIES: u32 = 3;
 
+/// How long a background upload to the remote cache may take before it is dropped,
+/// and how long `finish_uploads` waits for the ones still running
+pub const REMOTE_CACHE_UPLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
 fn calculate_chunk_size(file_size: u64) -> u64 {
     const MIN_CHUNK_SIZE: u64 = 5 * 1024 * 1024;
     const MAX_CHUNK_SIZE: u64 = 20 * 1024 * 1024;
@@ -96,12 +101,17 @@ fn get_alternate_urls(primary_url: &str) -> Vec<String> {
     alternates
 }
 
-/// Transform a URL to use a custom mirror domain
+/// Transform a URL to use a custom mirror domain. A mirror given with a scheme
+/// (`http://10.0.0.2:8080`, as printed by `zb serve`) replaces the scheme too.
 fn transform_url_to_mirror(url: &str, mirror_domain: &str) -> Option<String> {
-    if url.contains("ghcr.io") {
-        Some(url.replace("ghcr.io", mirror_domain))
+    if !url.contains("ghcr.io") {
+        return None;
+    }
+    if mirror_domain.contains("://") {
+        let (_, path) = url.split_once("ghcr.io")?;
+        Some(format!("{}{path}", mirror_domain.trim_end_matches('/')))
     } else {
-        None
+        Some(url.replace("ghcr.io", mirror_domain))
     }
 }
 
@@ -149,6 +159,13 @@ pub struct Downloader {
     token_cache: TokenCache,
     global_semaphore: Option<Arc<Semaphore>>,
     tls_config: Arc<rustls::ClientConfig>,
+    /// Only hand out blobs that are already cached
+    offline: AtomicBool,
+    /// Shared HTTP cache tried before the origin and filled after verified
+    /// downloads, addressed as `{remote_cache}/{sha256}`
+    remote_cache: std::sync::RwLock<Option<String>>,
+    /// Uploads to the remote cache still running in the background
+    uploads: std::sync::Mutex<JoinSet<()>>,
 }
 
 impl Downloader {
@@ -177,6 +194,9 @@ impl Downloader {
             token_cache: Arc::new(RwLock::new(HashMap::new())),
             global_semaphore: semaphore,
             tls_config,
+            offline: AtomicBool::new(false),
+            remote_cache: std::sync::RwLock::new(None),
+            uploads: std::sync::Mutex::new(JoinSet::new()),
         }
     }
 
@@ -202,6 +222,33 @@ impl Downloader {
         self.blob_cache.remove_blob(sha256).unwrap_or(false)
     }
 
+    pub fn has_blob(&self, sha256: &str) -> bool {
+        self.blob_cache.has_blob(sha256)
+    }
+
+    pub fn blob_cache(&self) -> &BlobCache {
+        &self.blob_cache
+    }
+
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.store(offline, Ordering::Relaxed);
+    }
+
+    pub fn set_remote_cache(&self, url: Option<String>) {
+        let url = url.map(|url| url.trim_end_matches('/').to_string());
+        *self.remote_cache.write().unwrap() = url;
+    }
+
+    /// Wait up to `REMOTE_CACHE_UPLOAD_TIMEOUT` for background uploads to the remote
+    /// cache. Call before exiting, since shutting down the runtime cancels them.
+    pub async fn finish_uploads(&self) {
+        let mut uploads = std::mem::take(&mut *self.uploads.lock().unwrap());
+        let _ = tokio::time::timeout(REMOTE_CACHE_UPLOAD_TIMEOUT, async {
+            while uploads.join_next().await.is_some() {}
+        })
+        .await;
+    }
+
     pub async fn download(&self, url: &str, expected_sha256: &str) -> Result<PathBuf, Error> {
         self.download_with_progress(url, expected_sha256, None, None)
             .await
@@ -225,12 +272,124 @@ impl Downloader {
             return Ok(self.blob_cache.blob_path(expected_sha256));
         }
 
+        // Bottles on disk, such as ones `zb bottle` wrote, are imported, not downloaded
+        if let Some(local_path) = url.strip_prefix("file://") {
+            return self
+                .import_local_file(Path::new(local_path), expected_sha256, name, progress)
+                .await;
+        }
+
+        if self.offline.load(Ordering::Relaxed) {
+            return Err(Error::OfflineUnavailable {
+                missing: vec![format!("bottle {}", name.as_deref().unwrap_or(url))],
+            });
+        }
+
+        let remote_cache = self.remote_cache.read().unwrap().clone();
+        if let Some(cache_url) = &remote_cache
+            && let Some(path) = self
+                .fetch_from_remote_cache(cache_url, expected_sha256, name.clone(), progress.clone())
+                .await
+        {
+            return Ok(path);
+        }
+
         // Get alternate mirror URLs (user-configured)
         let alternates = get_alternate_urls(url);
 
         // Always use racing to hit different CDN edges for faster downloads
-        self.download_with_racing(url, &alternates, expected_sha256, name, progress)
+        let path = self
+            .download_with_racing(url, &alternates, expected_sha256, name, progress)
+            .await?;
+
+        if let Some(cache_url) = &remote_cache {
+            self.upload_to_remote_cache(cache_url, expected_sha256, &path);
+        }
+        Ok(path)
+    }
+
+    /// Copy a local bottle into the blob cache, verifying it like a download
+    async fn import_local_file(
+        &self,
+        path: &Path,
+        expected_sha256: &str,
+        name: Option<String>,
+        progress: Option<DownloadProgressCallback>,
+    ) -> Result<PathBuf, Error> {
+        let blob_cache = self.blob_cache.clone();
+        let path = path.to_path_buf();
+        let sha256 = expected_sha256.to_string();
+        let total_bytes = tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(&path).map_err(|e| Error::FileError {
+                message: format!("failed to read bottle {}: {e}", path.display()),
+            })?;
+            let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+            blob_cache.import_blob(&sha256, &mut file)?;
+            Ok::<_, Error>(len)
+        })
+        .await
+        .map_err(|e| Error::FileError {
+            message: format!("bottle import task failed: {e}"),
+        })??;
+
+        if let (Some(cb), Some(n)) = (&progress, &name) {
+            cb(InstallProgress::DownloadCompleted {
+                name: n.clone(),
+                total_bytes,
+            });
+        }
+        Ok(self.blob_cache.blob_path(expected_sha256))
+    }
+
+    /// Try the remote cache first. Misses, network errors and blobs that fail
+    /// verification all fall back to the origin.
+    async fn fetch_from_remote_cache(
+        &self,
+        cache_url: &str,
+        expected_sha256: &str,
+        name: Option<String>,
+        progress: Option<DownloadProgressCallback>,
+    ) -> Option<PathBuf> {
+        let response = self
+            .client
+            .get(format!("{cache_url}/{expected_sha256}"))
+            .send()
+            .await
+            .ok()
+            .filter(|response| response.status().is_success())?;
+
+        download_response_internal(&self.blob_cache, response, expected_sha256, name, progress)
             .await
+            .ok()
+    }
+
+    /// Share a verified blob with the remote cache in the background, so a slow
+    /// cache never holds up the install; `finish_uploads` waits for it. Best effort:
+    /// a failed upload only costs the next machine a trip to the origin.
+    fn upload_to_remote_cache(&self, cache_url: &str, sha256: &str, blob_path: &Path) {
+        let client = self.client.clone();
+        let url = format!("{cache_url}/{sha256}");
+        let blob_path = blob_path.to_path_buf();
+
+        let mut uploads = self.uploads.lock().unwrap();
+        // Reap finished uploads so long sessions don't accumulate them
+        while uploads.try_join_next().is_some() {}
+        uploads.spawn(async move {
+            let Ok(file) = tokio::fs::File::open(&blob_path).await else {
+                return;
+            };
+            let Ok(metadata) = file.metadata().await else {
+                return;
+            };
+
+            let _ = client
+                .put(url)
+                .header(CONTENT_LENGTH, metadata.len())
+                .body(reqwest::Body::from(file))
+                .timeout(REMOTE_CACHE_UPLOAD_TIMEOUT)
+                .send()
+                .await;
+        });
     }
 
     /// Download with racing: start multiple parallel connections to the same URL
@@ -1188,6 +1347,28 @@ impl ParallelDownloader {
         self.downloader.remove_blob(sha256)
     }
 
+    pub fn has_blob(&self, sha256: &str) -> bool {
+        self.downloader.has_blob(sha256)
+    }
+
+    pub fn blob_cache(&self) -> &BlobCache {
+        self.downloader.blob_cache()
+    }
+
+    /// In offline mode only blobs already in the cache are served
+    pub fn set_offline(&self, offline: bool) {
+        self.downloader.set_offline(offline);
+    }
+
+    /// Read from and write verified blobs through to a shared HTTP cache
+    pub fn set_remote_cache(&self, url: Option<String>) {
+        self.downloader.set_remote_cache(url);
+    }
+
+    pub async fn finish_uploads(&self) {
+        self.downloader.finish_uploads().await;
+    }
+
     /// Download a single file (used for retries after corruption)
     pub async fn download_single(
         &self,
@@ -1345,6 +1526,23 @@ mod tests {
     use wiremock::matchers::{method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
+    #[test]
+    fn mirrors_keep_the_registry_path() {
+        let url = "https://ghcr.io/v2/homebrew/core/jq/blobs/sha256:abc";
+        assert_eq!(
+            transform_url_to_mirror(url, "mirror.example.com").as_deref(),
+            Some("https://mirror.example.com/v2/homebrew/core/jq/blobs/sha256:abc")
+        );
+        assert_eq!(
+            transform_url_to_mirror(url, "http://10.0.0.2:8080/").as_deref(),
+            Some("http://10.0.0.2:8080/v2/homebrew/core/jq/blobs/sha256:abc")
+        );
+        assert_eq!(
+            transform_url_to_mirror("https://example.com/jq.tar.gz", "http://10.0.0.2:8080"),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn valid_checksum_passes() {
         let mock_server = MockServer::start().await;
@@ -1406,6 +1604,207 @@ mod tests {
         assert!(!tmp_path.exists());
     }
 
+    #[tokio::test]
+    async fn remote_cache_hit_skips_the_origin() {
+        let mock_server = MockServer::start().await;
+        let content = b"hello world";
+        let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        Mock::given(method("GET"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(content.to_vec()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test.tar.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(content.to_vec()))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let tmp = TempDir::new().unwrap();
+        let downloader = Downloader::new(BlobCache::new(tmp.path()).unwrap());
+        downloader.set_remote_cache(Some(format!("{}/cache/", mock_server.uri())));
+
+        let url = format!("{}/test.tar.gz", mock_server.uri());
+        let blob_path = downloader.download(&url, sha256).await.unwrap();
+        assert_eq!(std::fs::read(&blob_path).unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn remote_cache_miss_uploads_after_verifying() {
+        let mock_server = MockServer::start().await;
+        let content = b"hello world";
+        let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        Mock::given(method("GET"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test.tar.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(content.to_vec()))
+            .mount(&mock_server)
+            .await;
+        // A failing upload must not fail the download
+        Mock::given(method("PUT"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let tmp = TempDir::new().unwrap();
+        let downloader = Downloader::new(BlobCache::new(tmp.path()).unwrap());
+        downloader.set_remote_cache(Some(format!("{}/cache", mock_server.uri())));
+
+        let url = format!("{}/test.tar.gz", mock_server.uri());
+        let blob_path = downloader.download(&url, sha256).await.unwrap();
+        assert_eq!(std::fs::read(&blob_path).unwrap(), content);
+
+        downloader.finish_uploads().await;
+        let requests = mock_server.received_requests().await.unwrap();
+        assert_eq!(upload(requests).body, content);
+    }
+
+    fn upload(requests: Vec<wiremock::Request>) -> wiremock::Request {
+        requests
+            .into_iter()
+            .find(|request| request.method.as_str() == "PUT")
+            .expect("no upload to the remote cache")
+    }
+
+    #[tokio::test]
+    async fn slow_remote_cache_upload_does_not_hold_up_the_download() {
+        let mock_server = MockServer::start().await;
+        let content = b"hello world";
+        let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        Mock::given(method("GET"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test.tar.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(content.to_vec()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(201).set_delay(Duration::from_secs(30)))
+            .mount(&mock_server)
+            .await;
+
+        let tmp = TempDir::new().unwrap();
+        let downloader = Downloader::new(BlobCache::new(tmp.path()).unwrap());
+        downloader.set_remote_cache(Some(format!("{}/cache", mock_server.uri())));
+
+        let url = format!("{}/test.tar.gz", mock_server.uri());
+        let started = Instant::now();
+        let blob_path = downloader.download(&url, sha256).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(std::fs::read(&blob_path).unwrap(), content);
+    }
+
+    #[tokio::test]
+    async fn finish_uploads_waits_for_background_uploads() {
+        let mock_server = MockServer::start().await;
+        let content = b"hello world";
+        let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        Mock::given(method("GET"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test.tar.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(content.to_vec()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(201).set_delay(Duration::from_millis(500)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let tmp = TempDir::new().unwrap();
+        let downloader = Downloader::new(BlobCache::new(tmp.path()).unwrap());
+        downloader.set_remote_cache(Some(format!("{}/cache", mock_server.uri())));
+
+        let url = format!("{}/test.tar.gz", mock_server.uri());
+        downloader.download(&url, sha256).await.unwrap();
+
+        // The upload is still waiting on the slow cache until it is awaited
+        let started = Instant::now();
+        downloader.finish_uploads().await;
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(downloader.uploads.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn corrupt_remote_cache_entry_falls_back_to_origin() {
+        let mock_server = MockServer::start().await;
+        let content = b"hello world";
+        let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+        Mock::given(method("GET"))
+            .and(path(format!("/cache/{sha256}")))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"tampered".to_vec()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test.tar.gz"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(content.to_vec()))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let tmp = TempDir::new().unwrap();
+        let downloader = Downloader::new(BlobCache::new(tmp.path()).unwrap());
+        downloader.set_remote_cache(Some(format!("{}/cache", mock_server.uri())));
+
+        let url = format!("{}/test.tar.gz", mock_server.uri());
+        let blob_path = downloader.download(&url, sha256).await.unwrap();
+        assert_eq!(std::fs::read(&blob_path).unwrap(), content);
+        downloader.finish_uploads().await;
+        upload(mock_server.received_requests().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn imports_bottles_from_file_urls() {
+        let tmp = TempDir::new().unwrap();
+        let sha256 = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        let local = tmp.path().join("hello.tar.gz");
+        std::fs::write(&local, b"hello world").unwrap();
+
+        let downloader = Downloader::new(BlobCache::new(&tmp.path().join("cache")).unwrap());
+        let url = format!("file://{}", local.display());
+        let blob_path = downloader.download(&url, sha256).await.unwrap();
+        assert_eq!(std::fs::read(&blob_path).unwrap(), b"hello world");
+
+        std::fs::write(&local, b"tampered").unwrap();
+        let wrong = "0".repeat(64);
+        assert!(matches!(
+            downloader.download(&url, &wrong).await,
+            Err(Error::ChecksumMismatch { .. })
+        ));
+        assert!(!downloader.has_blob(&wrong));
+    }
+
     #[tokio::test]
     async fn skips_download_if_blob_exists() {
         let mock_server = MockServer::start().await;
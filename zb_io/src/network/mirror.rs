@@ -0,0 +1,559 @@
+//! A small HTTP/1.1 server that exposes the local caches as a bottle mirror.
+//!
+//! Bottles are served at the paths ghcr.io uses
+//! (`/v2/homebrew/core/{name}/blobs/sha256:{sha256}`), so listing the server in
+//! `HOMEBREW_BOTTLE_MIRRORS` is all other machines need. Clients check every
+//! bottle against the sha256 in its formula, so the mirror doesn't have to be
+//! trusted. Cached formula JSON can also be served, under `/api/formula/{name}.json`
+//! with the bulk index at `/api/formula.json`.
+
+use std::io::{self, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::fs::File;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::network::cache::ApiCache;
+use crate::storage::blob::{BlobCache, is_sha256_hex};
+
+/// Longest request or header line accepted
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+
+const MAX_HEADERS: usize = 100;
+
+/// How long a connection may go without sending a complete request, covering both
+/// idle keep-alive connections and clients trickling in headers
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct MirrorServer {
+    blob_cache: BlobCache,
+    formulas: Option<CachedFormulas>,
+    request_timeout: Duration,
+}
+
+struct CachedFormulas {
+    cache: Mutex<ApiCache>,
+    /// The formula API base URL the cache entries were fetched from
+    base_url: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    method: String,
+    path: String,
+    range: Option<String>,
+    keep_alive: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Whole,
+    /// Inclusive start and end offsets
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+impl MirrorServer {
+    pub fn new(blob_cache: BlobCache) -> Self {
+        Self {
+            blob_cache,
+            formulas: None,
+            request_timeout: REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Close connections that don't send a complete request within `timeout`
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Also serve the formula JSON in `cache`, stored under URLs below `base_url`
+    pub fn with_formulas(mut self, cache: ApiCache, base_url: &str) -> Self {
+        self.formulas = Some(CachedFormulas {
+            cache: Mutex::new(cache),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        });
+        self
+    }
+
+    /// Answer requests on `listener` until the future is dropped
+    pub async fn serve(self, listener: TcpListener) {
+        let server = Arc::new(self);
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let server = server.clone();
+                    // A client hanging up mid-response is its own problem
+                    tokio::spawn(async move {
+                        let _ = server.handle_connection(stream).await;
+                    });
+                }
+                // Usually out of file descriptors; back off instead of spinning
+                Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+            }
+        }
+    }
+
+    async fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        loop {
+            let Ok(request) =
+                tokio::time::timeout(self.request_timeout, read_request(&mut reader)).await
+            else {
+                break;
+            };
+            let Some(request) = request? else {
+                break;
+            };
+            self.respond(&request, &mut writer).await?;
+            if !request.keep_alive {
+                break;
+            }
+        }
+
+        writer.shutdown().await
+    }
+
+    async fn respond<W: AsyncWrite + Unpin>(
+        &self,
+        request: &Request,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let head_only = request.method == "HEAD";
+        if !head_only && request.method != "GET" {
+            return write_head(
+                writer,
+                "405 Method Not Allowed",
+                &[("Allow", "GET, HEAD".to_string())],
+                0,
+                false,
+            )
+            .await;
+        }
+
+        if let Some(sha256) = blob_sha256(&request.path) {
+            return self.send_blob(sha256, request, head_only, writer).await;
+        }
+
+        match self.formula_json(&request.path) {
+            Some(body) => {
+                write_head(
+                    writer,
+                    "200 OK",
+                    &[("Content-Type", "application/json".to_string())],
+                    body.len() as u64,
+                    request.keep_alive,
+                )
+                .await?;
+                if !head_only {
+                    writer.write_all(body.as_bytes()).await?;
+                }
+                writer.flush().await
+            }
+            None => write_head(writer, "404 Not Found", &[], 0, request.keep_alive).await,
+        }
+    }
+
+    async fn send_blob<W: AsyncWrite + Unpin>(
+        &self,
+        sha256: &str,
+        request: &Request,
+        head_only: bool,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        // Blobs are renamed into place once complete, so an open file is never partial
+        let mut file = match File::open(self.blob_cache.blob_path(sha256)).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return write_head(writer, "404 Not Found", &[], 0, request.keep_alive).await;
+            }
+            Err(e) => return Err(e),
+        };
+        let size = file.metadata().await?.len();
+
+        let mut headers = vec![
+            ("Content-Type", "application/octet-stream".to_string()),
+            ("Accept-Ranges", "bytes".to_string()),
+        ];
+        let (status, start, length) = match parse_range(request.range.as_deref(), size) {
+            ByteRange::Whole => ("200 OK", 0, size),
+            ByteRange::Partial(start, end) => {
+                headers.push(("Content-Range", format!("bytes {start}-{end}/{size}")));
+                ("206 Partial Content", start, end - start + 1)
+            }
+            ByteRange::Unsatisfiable => {
+                headers.push(("Content-Range", format!("bytes */{size}")));
+                return write_head(
+                    writer,
+                    "416 Range Not Satisfiable",
+                    &headers,
+                    0,
+                    request.keep_alive,
+                )
+                .await;
+            }
+        };
+
+        write_head(writer, status, &headers, length, request.keep_alive).await?;
+        if !head_only {
+            file.seek(SeekFrom::Start(start)).await?;
+            tokio::io::copy(&mut file.take(length), writer).await?;
+        }
+        writer.flush().await
+    }
+
+    fn formula_json(&self, path: &str) -> Option<String> {
+        let formulas = self.formulas.as_ref()?;
+        let rest = path.strip_prefix("/api/formula")?;
+
+        // Mirror the URLs `ApiClient` caches responses under
+        let url = if rest == ".json" {
+            format!("{}.json", formulas.base_url)
+        } else {
+            let name = rest.strip_prefix('/')?.strip_suffix(".json")?;
+            if name.is_empty() || name.contains('/') {
+                return None;
+            }
+            format!("{}/{name}.json", formulas.base_url)
+        };
+
+        let cache = formulas.cache.lock().ok()?;
+        cache.get(&url).map(|entry| entry.body)
+    }
+}
+
+async fn write_head<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: &str,
+    headers: &[(&str, String)],
+    content_length: u64,
+    keep_alive: bool,
+) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {status}\r\nContent-Length: {content_length}\r\n");
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    if !keep_alive {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// Read one request's line and headers, or `None` once the client is done.
+/// Only GET and HEAD are served, so request bodies are never read.
+async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<Request>> {
+    let Some(request_line) = read_line(reader).await? else {
+        return Ok(None);
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid_data("malformed request line"));
+    };
+    let path = target.split('?').next().unwrap_or(target);
+
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        range: None,
+        // Anything with a body we won't read has to end the connection
+        keep_alive: version == "HTTP/1.1" && matches!(method, "GET" | "HEAD"),
+    };
+
+    for _ in 0..MAX_HEADERS {
+        let line = read_line(reader)
+            .await?
+            .ok_or_else(|| invalid_data("connection closed mid-request"))?;
+        if line.is_empty() {
+            return Ok(Some(request));
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("range") {
+            request.range = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("connection") && value.eq_ignore_ascii_case("close") {
+            request.keep_alive = false;
+        }
+    }
+
+    Err(invalid_data("too many headers"))
+}
+
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    let n = (&mut *reader)
+        .take(MAX_LINE_LENGTH)
+        .read_line(&mut line)
+        .await?;
+    if n == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') {
+        return Err(invalid_data("request line too long"));
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// The digest in a registry blob path such as `/v2/homebrew/core/jq/blobs/sha256:{sha256}`
+fn blob_sha256(path: &str) -> Option<&str> {
+    let (parent, digest) = path.rsplit_once('/')?;
+    if !parent.ends_with("/blobs") {
+        return None;
+    }
+    let sha256 = digest
+        .strip_prefix("sha256:")
+        .or_else(|| digest.strip_prefix("sha256%3A"))?;
+    is_sha256_hex(sha256).then_some(sha256)
+}
+
+/// Interpret a `Range` header for a file of `size` bytes. Only single `bytes=`
+/// ranges are honored; anything else is answered with the whole file, which
+/// HTTP allows.
+fn parse_range(header: Option<&str>, size: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Whole;
+    };
+    if spec.contains(',') {
+        return ByteRange::Whole;
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return ByteRange::Whole;
+    };
+    let (first, last) = (first.trim(), last.trim());
+
+    // `bytes=-N` asks for the last N bytes
+    if first.is_empty() {
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if size == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => ByteRange::Partial(size.saturating_sub(suffix), size - 1),
+            Err(_) => ByteRange::Whole,
+        };
+    }
+
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Whole;
+    };
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Whole,
+        }
+    };
+
+    if start >= size {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial(start, end.min(size - 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::cache::CacheEntry;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(parse_range(None, 100), ByteRange::Whole);
+        assert_eq!(
+            parse_range(Some("bytes=0-0"), 100),
+            ByteRange::Partial(0, 0)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=10-"), 100),
+            ByteRange::Partial(10, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=90-500"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-10"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=-500"), 100),
+            ByteRange::Partial(0, 99)
+        );
+        assert_eq!(
+            parse_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Whole);
+        assert_eq!(parse_range(Some("bytes=9-3"), 100), ByteRange::Whole);
+        assert_eq!(parse_range(Some("items=0-1"), 100), ByteRange::Whole);
+    }
+
+    #[test]
+    fn recognizes_registry_blob_paths() {
+        let sha = "ab".repeat(32);
+        assert_eq!(
+            blob_sha256(&format!("/v2/homebrew/core/jq/blobs/sha256:{sha}")),
+            Some(sha.as_str())
+        );
+        assert_eq!(
+            blob_sha256(&format!("/v2/homebrew/core/jq/blobs/sha256%3A{sha}")),
+            Some(sha.as_str())
+        );
+        assert_eq!(blob_sha256("/v2/homebrew/core/jq/blobs/sha256:abc"), None);
+        assert_eq!(
+            blob_sha256(&format!("/v2/homebrew/core/jq/manifests/sha256:{sha}")),
+            None
+        );
+        assert_eq!(
+            blob_sha256(&format!("/blobs/sha256:{}", "AB".repeat(32))),
+            None
+        );
+    }
+
+    async fn start_server(server: MirrorServer) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(server.serve(listener));
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn serves_cached_blobs_with_ranges() {
+        let tmp = TempDir::new().unwrap();
+        let blob_cache = BlobCache::new(tmp.path()).unwrap();
+        let sha = "cd".repeat(32);
+        let mut writer = blob_cache.start_write(&sha).unwrap();
+        writer.write_all(b"0123456789").unwrap();
+        writer.commit().unwrap();
+
+        let base = start_server(MirrorServer::new(blob_cache)).await;
+        let url = format!("{base}/v2/homebrew/core/foo/blobs/sha256:{sha}");
+        let client = reqwest::Client::new();
+
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["accept-ranges"], "bytes");
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"0123456789");
+
+        let response = client
+            .get(&url)
+            .header("Range", "bytes=2-5")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 206);
+        assert_eq!(response.headers()["content-range"], "bytes 2-5/10");
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"2345");
+
+        let response = client
+            .get(&url)
+            .header("Range", "bytes=20-")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 416);
+
+        let response = client.head(&url).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-length"], "10");
+
+        let missing = format!(
+            "{base}/v2/homebrew/core/foo/blobs/sha256:{}",
+            "ef".repeat(32)
+        );
+        assert_eq!(client.get(&missing).send().await.unwrap().status(), 404);
+        assert_eq!(client.post(&url).send().await.unwrap().status(), 405);
+    }
+
+    #[tokio::test]
+    async fn closes_idle_and_stalled_connections() {
+        let tmp = TempDir::new().unwrap();
+        let server = MirrorServer::new(BlobCache::new(tmp.path()).unwrap())
+            .with_request_timeout(Duration::from_millis(200));
+        let base = start_server(server).await;
+        let addr = base.trim_start_matches("http://");
+
+        // One connection never sends anything, the other stops mid-headers
+        for sent in [&b""[..], b"GET /v2/ HTTP/1.1\r\nHost: mirror\r\n"] {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(sent).await.unwrap();
+            let mut buf = Vec::new();
+            let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut buf))
+                .await
+                .expect("connection was not closed");
+            assert_eq!(read.unwrap(), 0);
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_cached_formula_json_when_enabled() {
+        let tmp = TempDir::new().unwrap();
+        let blob_cache = BlobCache::new(tmp.path()).unwrap();
+        let base_url = "https://formulae.brew.sh/api/formula";
+
+        let api_cache = ApiCache::in_memory().unwrap();
+        for (url, body) in [
+            (format!("{base_url}/jq.json"), r#"{"name":"jq"}"#),
+            (format!("{base_url}.json"), r#"[{"name":"jq"}]"#),
+        ] {
+            let entry = CacheEntry {
+                etag: None,
+                last_modified: None,
+                body: body.to_string(),
+            };
+            api_cache.put(&url, &entry).unwrap();
+        }
+
+        let client = reqwest::Client::new();
+        let without = start_server(MirrorServer::new(blob_cache.clone())).await;
+        let response = client
+            .get(format!("{without}/api/formula/jq.json"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        let with =
+            start_server(MirrorServer::new(blob_cache).with_formulas(api_cache, base_url)).await;
+        let response = client
+            .get(format!("{with}/api/formula/jq.json"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), r#"{"name":"jq"}"#);
+
+        let index = client
+            .get(format!("{with}/api/formula.json"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(index.text().await.unwrap(), r#"[{"name":"jq"}]"#);
+
+        let response = client
+            .get(format!("{with}/api/formula/wget.json"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+    }
+}
@@ -1,9 +1,11 @@
 pub mod api;
 pub mod cache;
 pub mod download;
+pub mod mirror;
 
 pub use api::ApiClient;
 pub use cache::{ApiCache, CacheEntry};
 pub use download::{
     DownloadProgressCallback, DownloadRequest, DownloadResult, Downloader, ParallelDownloader,
 };
+pub use mirror::MirrorServer;
@@ -0,0 +1,359 @@
+//! Portable snapshots of the caches for machines without network access.
+//!
+//! An archive is a plain tar (bottles are already compressed) holding
+//! `manifest.json` first, then one `formulas/{name}.json` per formula and the
+//! bottles under `blobs/{sha256}.tar.gz`, the same names `BlobCache` uses.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::storage::blob::{BlobCache, is_sha256_hex};
+use zb_core::Error;
+
+/// Bumped whenever the archive layout changes incompatibly
+pub const CACHE_ARCHIVE_FORMAT: u32 = 1;
+
+const MANIFEST_PATH: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CacheManifest {
+    pub format: u32,
+    /// Requested names that resolved to a differently named formula
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub renamed: BTreeMap<String, String>,
+    pub bottles: Vec<ArchivedBottle>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ArchivedBottle {
+    pub name: String,
+    pub version: String,
+    pub tag: String,
+    pub sha256: String,
+}
+
+/// An archive's manifest and formula JSON, keyed by formula name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheArchive {
+    pub manifest: CacheManifest,
+    pub formulas: BTreeMap<String, String>,
+}
+
+fn file_error(context: &str, path: &Path, e: impl std::fmt::Display) -> Error {
+    Error::FileError {
+        message: format!("{context} {}: {e}", path.display()),
+    }
+}
+
+/// Write `archive` to `path`, reading every bottle in the manifest from `blob_cache`
+pub fn write_cache_archive(
+    path: &Path,
+    archive: &CacheArchive,
+    blob_cache: &BlobCache,
+) -> Result<(), Error> {
+    let mut part_path = path.as_os_str().to_owned();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+    let result = write_entries(&part_path, archive, blob_cache).and_then(|()| {
+        fs::rename(&part_path, path).map_err(|e| file_error("failed to write", path, e))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&part_path);
+    }
+    result
+}
+
+fn write_entries(path: &Path, archive: &CacheArchive, blob_cache: &BlobCache) -> Result<(), Error> {
+    let write_error = |e: std::io::Error| file_error("failed to write", path, e);
+
+    let file = fs::File::create(path).map_err(write_error)?;
+    let mut builder = tar::Builder::new(file);
+
+    let manifest = serde_json::to_vec_pretty(&archive.manifest).map_err(|e| Error::FileError {
+        message: format!("failed to serialize cache manifest: {e}"),
+    })?;
+    append_bytes(&mut builder, MANIFEST_PATH, &manifest).map_err(write_error)?;
+
+    for (name, body) in &archive.formulas {
+        append_bytes(
+            &mut builder,
+            &format!("formulas/{name}.json"),
+            body.as_bytes(),
+        )
+        .map_err(write_error)?;
+    }
+
+    let shas: BTreeSet<&str> = archive
+        .manifest
+        .bottles
+        .iter()
+        .map(|bottle| bottle.sha256.as_str())
+        .collect();
+    for sha256 in shas {
+        let blob_path = blob_cache.blob_path(sha256);
+        builder
+            .append_path_with_name(&blob_path, format!("blobs/{sha256}.tar.gz"))
+            .map_err(|e| file_error("failed to add", &blob_path, e))?;
+    }
+
+    builder.into_inner().map_err(write_error)?;
+    Ok(())
+}
+
+fn append_bytes(
+    builder: &mut tar::Builder<fs::File>,
+    name: &str,
+    data: &[u8],
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append_data(&mut header, name, data)
+}
+
+/// Read an archive written by `write_cache_archive`, loading its bottles into
+/// `blob_cache`. Every bottle is checked against the manifest's sha256, and an
+/// archive missing any bottle it lists is rejected. Also returns how many
+/// bottles were new to the cache.
+pub fn import_cache_archive(
+    path: &Path,
+    blob_cache: &BlobCache,
+) -> Result<(CacheArchive, usize), Error> {
+    let read_error = |e: std::io::Error| file_error("failed to read", path, e);
+
+    let file = fs::File::open(path).map_err(read_error)?;
+    let mut tar = tar::Archive::new(file);
+
+    let mut manifest: Option<CacheManifest> = None;
+    let mut formulas = BTreeMap::new();
+    let mut seen = BTreeSet::new();
+    let mut added = 0;
+
+    for entry in tar.entries().map_err(read_error)? {
+        let mut entry = entry.map_err(read_error)?;
+        let entry_path = entry
+            .path()
+            .map_err(read_error)?
+            .to_string_lossy()
+            .to_string();
+
+        if entry_path == MANIFEST_PATH {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).map_err(read_error)?;
+            manifest = Some(parse_manifest(&contents)?);
+            continue;
+        }
+
+        let Some(manifest) = &manifest else {
+            return Err(file_error(
+                "invalid cache archive",
+                path,
+                "manifest.json must be the first entry",
+            ));
+        };
+
+        if let Some(name) = entry_path
+            .strip_prefix("formulas/")
+            .and_then(|rest| rest.strip_suffix(".json"))
+        {
+            let mut body = String::new();
+            entry.read_to_string(&mut body).map_err(read_error)?;
+            formulas.insert(name.to_string(), body);
+        } else if let Some(rest) = entry_path.strip_prefix("blobs/") {
+            let Some(sha256) = rest
+                .strip_suffix(".tar.gz")
+                .filter(|sha256| is_sha256_hex(sha256))
+            else {
+                return Err(file_error(
+                    "invalid cache archive",
+                    path,
+                    format!("unexpected entry {entry_path}"),
+                ));
+            };
+            if !manifest.bottles.iter().any(|b| b.sha256 == sha256) {
+                return Err(file_error(
+                    "invalid cache archive",
+                    path,
+                    format!("blob {sha256} is not listed in the manifest"),
+                ));
+            }
+            if blob_cache.import_blob(sha256, &mut entry)? {
+                added += 1;
+            }
+            seen.insert(sha256.to_string());
+        }
+    }
+
+    let Some(manifest) = manifest else {
+        return Err(file_error(
+            "invalid cache archive",
+            path,
+            "manifest.json is missing",
+        ));
+    };
+
+    let missing: Vec<String> = manifest
+        .bottles
+        .iter()
+        .filter(|bottle| !seen.contains(&bottle.sha256))
+        .map(|bottle| bottle.name.clone())
+        .collect();
+    if !missing.is_empty() {
+        return Err(file_error(
+            "incomplete cache archive",
+            path,
+            format!("no bottle for {}", missing.join(", ")),
+        ));
+    }
+
+    Ok((CacheArchive { manifest, formulas }, added))
+}
+
+fn parse_manifest(contents: &str) -> Result<CacheManifest, Error> {
+    let manifest: CacheManifest = serde_json::from_str(contents).map_err(|e| Error::FileError {
+        message: format!("failed to parse cache manifest: {e}"),
+    })?;
+
+    if manifest.format != CACHE_ARCHIVE_FORMAT {
+        return Err(Error::FileError {
+            message: format!(
+                "unsupported cache archive format {} (expected {CACHE_ARCHIVE_FORMAT})",
+                manifest.format
+            ),
+        });
+    }
+
+    if let Some(bottle) = manifest.bottles.iter().find(|b| !is_sha256_hex(&b.sha256)) {
+        return Err(Error::FileError {
+            message: format!(
+                "invalid sha256 {:?} for {} in cache manifest",
+                bottle.sha256, bottle.name
+            ),
+        });
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn archive_for(sha256: &str) -> CacheArchive {
+        CacheArchive {
+            manifest: CacheManifest {
+                format: CACHE_ARCHIVE_FORMAT,
+                renamed: BTreeMap::new(),
+                bottles: vec![ArchivedBottle {
+                    name: "jq".to_string(),
+                    version: "1.7.1".to_string(),
+                    tag: "arm64_sonoma".to_string(),
+                    sha256: sha256.to_string(),
+                }],
+            },
+            formulas: BTreeMap::from([("jq".to_string(), "{}".to_string())]),
+        }
+    }
+
+    fn cache_blob(cache: &BlobCache, sha256: &str, data: &[u8]) {
+        let mut writer = cache.start_write(sha256).unwrap();
+        writer.write_all(data).unwrap();
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn round_trips_blobs_and_formulas() {
+        let tmp = TempDir::new().unwrap();
+        let source = BlobCache::new(&tmp.path().join("source")).unwrap();
+        let target = BlobCache::new(&tmp.path().join("target")).unwrap();
+
+        let data = b"bottle";
+        let sha = format!("{:x}", Sha256::digest(data));
+        cache_blob(&source, &sha, data);
+
+        let path = tmp.path().join("cache.tar");
+        let archive = archive_for(&sha);
+        write_cache_archive(&path, &archive, &source).unwrap();
+
+        let (imported, added) = import_cache_archive(&path, &target).unwrap();
+        assert_eq!(imported, archive);
+        assert_eq!(added, 1);
+        assert_eq!(fs::read(target.blob_path(&sha)).unwrap(), data);
+
+        let (_, added) = import_cache_archive(&path, &target).unwrap();
+        assert_eq!(added, 0);
+    }
+
+    #[test]
+    fn rejects_blobs_that_do_not_match_the_manifest() {
+        let tmp = TempDir::new().unwrap();
+        let source = BlobCache::new(&tmp.path().join("source")).unwrap();
+        let target = BlobCache::new(&tmp.path().join("target")).unwrap();
+
+        let sha = "ab".repeat(32);
+        cache_blob(&source, &sha, b"tampered");
+
+        let path = tmp.path().join("cache.tar");
+        write_cache_archive(&path, &archive_for(&sha), &source).unwrap();
+
+        let err = import_cache_archive(&path, &target).unwrap_err();
+        assert!(matches!(err, Error::ChecksumMismatch { .. }));
+        assert!(!target.has_blob(&sha));
+    }
+
+    /// Write a tar by hand, since `tar::Builder` refuses paths containing `..`
+    fn raw_archive(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut builder = tar::Builder::new(fs::File::create(path).unwrap());
+        for (name, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap();
+    }
+
+    #[test]
+    fn rejects_blob_paths_that_are_not_digests() {
+        let tmp = TempDir::new().unwrap();
+        let target = BlobCache::new(&tmp.path().join("cache")).unwrap();
+        let manifest = serde_json::to_vec(&archive_for(&"ab".repeat(32)).manifest).unwrap();
+
+        let path = tmp.path().join("cache.tar");
+        raw_archive(
+            &path,
+            &[
+                (MANIFEST_PATH, &manifest),
+                ("blobs/../../x.tar.gz", b"escaped"),
+            ],
+        );
+
+        let err = import_cache_archive(&path, &target).unwrap_err();
+        assert!(err.to_string().contains("unexpected entry"), "{err}");
+        assert!(!tmp.path().join("x.tar.gz").exists());
+    }
+
+    #[test]
+    fn rejects_manifests_with_malformed_sha256() {
+        let tmp = TempDir::new().unwrap();
+        let target = BlobCache::new(&tmp.path().join("cache")).unwrap();
+
+        for sha256 in ["../../x", &"AB".repeat(32), "abc"] {
+            let manifest = serde_json::to_vec(&archive_for(sha256).manifest).unwrap();
+            let path = tmp.path().join("cache.tar");
+            raw_archive(&path, &[(MANIFEST_PATH, &manifest)]);
+
+            let err = import_cache_archive(&path, &target).unwrap_err();
+            assert!(err.to_string().contains("invalid sha256"), "{err}");
+        }
+    }
+}
@@ -1,9 +1,18 @@
 use std::fs;
-use std::io::{self, Seek, SeekFrom, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
+use sha2::{Digest, Sha256};
 use zb_core::Error;
 
+/// Whether `s` is a digest the way blobs are named: 64 lowercase hex characters.
+/// Anything else could escape the blobs directory once joined onto it.
+pub(crate) fn is_sha256_hex(s: &str) -> bool {
+    s.len() == 64
+        && s.bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
 #[derive(Clone)]
 pub struct BlobCache {
     blobs_dir: PathBuf,
@@ -40,6 +49,40 @@ impl BlobCache {
         }
     }
 
+    /// Copy a blob from `reader` into the cache, verifying that it hashes to `sha256`.
+    /// Returns `false` without reading anything when the blob is already cached.
+    pub fn import_blob(&self, sha256: &str, reader: &mut impl Read) -> Result<bool, Error> {
+        if self.has_blob(sha256) {
+            return Ok(false);
+        }
+
+        let io_error = |e: io::Error| Error::FileError {
+            message: format!("failed to import blob {sha256}: {e}"),
+        };
+        let mut writer = self.start_write(sha256).map_err(io_error)?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf).map_err(io_error)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            writer.write_all(&buf[..n]).map_err(io_error)?;
+        }
+
+        let actual = format!("{:x}", hasher.finalize());
+        if actual != sha256 {
+            return Err(Error::ChecksumMismatch {
+                expected: sha256.to_string(),
+                actual,
+            });
+        }
+
+        writer.commit()?;
+        Ok(true)
+    }
+
     pub fn start_write(&self, sha256: &str) -> io::Result<BlobWriter> {
         let final_path = self.blob_path(sha256);
         // Use unique temp filename to avoid corruption from concurrent racing downloads
@@ -194,6 +237,24 @@ mod tests {
         assert!(!cache.has_blob(sha));
     }
 
+    #[test]
+    fn import_blob_verifies_checksum() {
+        let tmp = TempDir::new().unwrap();
+        let cache = BlobCache::new(tmp.path()).unwrap();
+
+        let data = b"bottle bytes";
+        let sha = format!("{:x}", Sha256::digest(data));
+
+        let wrong = "0".repeat(64);
+        let err = cache.import_blob(&wrong, &mut &data[..]).unwrap_err();
+        assert!(matches!(err, Error::ChecksumMismatch { .. }));
+        assert!(!cache.has_blob(&wrong));
+
+        assert!(cache.import_blob(&sha, &mut &data[..]).unwrap());
+        assert_eq!(fs::read(cache.blob_path(&sha)).unwrap(), data);
+        assert!(!cache.import_blob(&sha, &mut &data[..]).unwrap());
+    }
+
     #[test]
     fn remove_blob_returns_false_for_nonexistent() {
         let tmp = TempDir::new().unwrap();
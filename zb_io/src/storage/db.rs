@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use rusqlite::{Connection, Transaction, params};
@@ -14,6 +15,20 @@ pub struct InstalledKeg {
     pub version: String,
     pub store_key: String,
     pub installed_at: i64,
+    /// Whether the keg was asked for by the user, as opposed to pulled in as a dependency
+    pub requested: bool,
+    /// Third-party tap (`user/repo`) the formula came from; `None` for homebrew/core
+    pub tap: Option<String>,
+}
+
+impl InstalledKeg {
+    /// The name the keg's formula is resolved by: `user/repo/name` for tap formulas
+    pub fn formula_name(&self) -> String {
+        match &self.tap {
+            Some(tap) => format!("{tap}/{}", self.name),
+            None => self.name.clone(),
+        }
+    }
 }
 
 impl Database {
@@ -44,7 +59,9 @@ impl Database {
                 name TEXT PRIMARY KEY,
                 version TEXT NOT NULL,
                 store_key TEXT NOT NULL,
-                installed_at INTEGER NOT NULL
+                installed_at INTEGER NOT NULL,
+                requested INTEGER NOT NULL DEFAULT 1,
+                tap TEXT
             );
 
             CREATE TABLE IF NOT EXISTS store_refs (
@@ -59,12 +76,71 @@ impl Database {
                 target_path TEXT NOT NULL,
                 PRIMARY KEY (name, linked_path)
             );
+
+            CREATE TABLE IF NOT EXISTS keg_dependencies (
+                name TEXT NOT NULL,
+                dependency TEXT NOT NULL,
+                PRIMARY KEY (name, dependency)
+            );
+
+            CREATE TABLE IF NOT EXISTS keg_aliases (
+                alias TEXT PRIMARY KEY,
+                name TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS taps (
+                name TEXT PRIMARY KEY,
+                source TEXT NOT NULL
+            );
             ",
         )
         .map_err(|e| Error::StoreCorruption {
             message: format!("failed to initialize schema: {e}"),
         })?;
 
+        Self::migrate_schema(conn)
+    }
+
+    /// Bring databases created by older versions up to the current schema
+    fn migrate_schema(conn: &Connection) -> Result<(), Error> {
+        let has_column = |table: &str, column: &str| -> Result<bool, Error> {
+            let mut stmt = conn
+                .prepare(&format!("PRAGMA table_info({table})"))
+                .map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to inspect schema: {e}"),
+                })?;
+            let columns = stmt
+                .query_map([], |row| row.get::<_, String>(1))
+                .map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to inspect schema: {e}"),
+                })?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to inspect schema: {e}"),
+                })?;
+            Ok(columns.iter().any(|c| c == column))
+        };
+
+        // Kegs installed before install reasons were tracked are treated as requested,
+        // so nothing the user may have asked for is ever autoremoved
+        if !has_column("installed_kegs", "requested")? {
+            conn.execute(
+                "ALTER TABLE installed_kegs ADD COLUMN requested INTEGER NOT NULL DEFAULT 1",
+                [],
+            )
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to migrate schema: {e}"),
+            })?;
+        }
+
+        // Kegs installed before taps were recorded came from homebrew/core
+        if !has_column("installed_kegs", "tap")? {
+            conn.execute("ALTER TABLE installed_kegs ADD COLUMN tap TEXT", [])
+                .map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to migrate schema: {e}"),
+                })?;
+        }
+
         Ok(())
     }
 
@@ -82,7 +158,8 @@ impl Database {
     pub fn get_installed(&self, name: &str) -> Option<InstalledKeg> {
         self.conn
             .query_row(
-                "SELECT name, version, store_key, installed_at FROM installed_kegs WHERE name = ?1",
+                "SELECT name, version, store_key, installed_at, requested, tap
+                 FROM installed_kegs WHERE name = ?1",
                 params![name],
                 |row| {
                     Ok(InstalledKeg {
@@ -90,17 +167,44 @@ impl Database {
                         version: row.get(1)?,
                         store_key: row.get(2)?,
                         installed_at: row.get(3)?,
+                        requested: row.get(4)?,
+                        tap: row.get(5)?,
                     })
                 },
             )
             .ok()
     }
 
+    /// The installed keg behind `name`, which may be an alias or old name of the
+    /// formula recorded when the keg was installed, or `user/repo/name` for a keg
+    /// installed from that tap
+    pub fn resolve_installed_name(&self, name: &str) -> Option<String> {
+        if self.get_installed(name).is_some() {
+            return Some(name.to_string());
+        }
+        if let Some((tap, formula)) = zb_core::split_tap_name(name)
+            && let Some(keg) = self.get_installed(formula)
+        {
+            return (keg.tap.as_deref() == Some(tap)).then_some(keg.name);
+        }
+
+        self.conn
+            .query_row(
+                "SELECT a.name FROM keg_aliases a
+                 JOIN installed_kegs k ON k.name = a.name
+                 WHERE a.alias = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .ok()
+    }
+
     pub fn list_installed(&self) -> Result<Vec<InstalledKeg>, Error> {
         let mut stmt = self
             .conn
             .prepare(
-                "SELECT name, version, store_key, installed_at FROM installed_kegs ORDER BY name",
+                "SELECT name, version, store_key, installed_at, requested, tap
+                 FROM installed_kegs ORDER BY name",
             )
             .map_err(|e| Error::StoreCorruption {
                 message: format!("failed to prepare statement: {e}"),
@@ -113,6 +217,8 @@ impl Database {
                     version: row.get(1)?,
                     store_key: row.get(2)?,
                     installed_at: row.get(3)?,
+                    requested: row.get(4)?,
+                    tap: row.get(5)?,
                 })
             })
             .map_err(|e| Error::StoreCorruption {
@@ -126,6 +232,88 @@ impl Database {
         Ok(kegs)
     }
 
+    /// Direct dependencies recorded for a keg when it was installed
+    pub fn get_dependencies(&self, name: &str) -> Result<Vec<String>, Error> {
+        self.query_names(
+            "SELECT dependency FROM keg_dependencies WHERE name = ?1 ORDER BY dependency",
+            name,
+        )
+    }
+
+    /// Installed kegs that directly depend on `name`
+    pub fn get_dependents(&self, name: &str) -> Result<Vec<String>, Error> {
+        self.query_names(
+            "SELECT d.name FROM keg_dependencies d
+             JOIN installed_kegs k ON k.name = d.name
+             WHERE d.dependency = ?1 ORDER BY d.name",
+            name,
+        )
+    }
+
+    fn query_names(&self, sql: &str, name: &str) -> Result<Vec<String>, Error> {
+        let mut stmt = self.conn.prepare(sql).map_err(|e| Error::StoreCorruption {
+            message: format!("failed to prepare statement: {e}"),
+        })?;
+
+        let names = stmt
+            .query_map(params![name], |row| row.get(0))
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to query keg dependencies: {e}"),
+            })?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to collect results: {e}"),
+            })?;
+
+        Ok(names)
+    }
+
+    /// Registered third-party taps, mapped to the directory or URL serving their formulas
+    pub fn list_taps(&self) -> Result<BTreeMap<String, String>, Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, source FROM taps ORDER BY name")
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to prepare statement: {e}"),
+            })?;
+
+        let taps = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to query taps: {e}"),
+            })?
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to collect results: {e}"),
+            })?;
+
+        Ok(taps)
+    }
+
+    /// Register a tap, replacing the source of one with the same name
+    pub fn add_tap(&self, name: &str, source: &str) -> Result<(), Error> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO taps (name, source) VALUES (?1, ?2)",
+                params![name, source],
+            )
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to record tap: {e}"),
+            })?;
+        Ok(())
+    }
+
+    /// Returns whether the tap was registered
+    pub fn remove_tap(&self, name: &str) -> Result<bool, Error> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM taps WHERE name = ?1", params![name])
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to remove tap: {e}"),
+            })?;
+        Ok(removed > 0)
+    }
+
     pub fn get_store_refcount(&self, store_key: &str) -> i64 {
         self.conn
             .query_row(
@@ -163,22 +351,70 @@ pub struct InstallTransaction<'a> {
 }
 
 impl<'a> InstallTransaction<'a> {
-    pub fn record_install(&self, name: &str, version: &str, store_key: &str) -> Result<(), Error> {
+    /// Record a keg as installed. If the formula already has a record (a reinstall or
+    /// an upgrade), the row is replaced and its store ref moves to the new store key.
+    /// A keg that was ever requested stays requested when reinstalled as a dependency.
+    pub fn record_install(
+        &self,
+        name: &str,
+        version: &str,
+        store_key: &str,
+        requested: bool,
+    ) -> Result<(), Error> {
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
+        let previous: Option<(String, String, bool)> = self
+            .tx
+            .query_row(
+                "SELECT version, store_key, requested FROM installed_kegs WHERE name = ?1",
+                params![name],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .ok();
+        let requested = requested || previous.as_ref().is_some_and(|(_, _, r)| *r);
+
         self.tx
             .execute(
-                "INSERT OR REPLACE INTO installed_kegs (name, version, store_key, installed_at)
-                 VALUES (?1, ?2, ?3, ?4)",
-                params![name, version, store_key, now],
+                "INSERT OR REPLACE INTO installed_kegs
+                 (name, version, store_key, installed_at, requested)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![name, version, store_key, now, requested],
             )
             .map_err(|e| Error::StoreCorruption {
                 message: format!("failed to record install: {e}"),
             })?;
 
+        if let Some((previous_version, previous_key, _)) = previous {
+            // Links recorded for the replaced version no longer exist
+            if previous_version != version {
+                self.tx
+                    .execute(
+                        "DELETE FROM keg_files WHERE name = ?1 AND version = ?2",
+                        params![name, previous_version],
+                    )
+                    .map_err(|e| Error::StoreCorruption {
+                        message: format!("failed to remove keg files records: {e}"),
+                    })?;
+            }
+
+            // A reinstall of the same store entry keeps its existing ref
+            if previous_key == store_key {
+                return Ok(());
+            }
+
+            self.tx
+                .execute(
+                    "UPDATE store_refs SET refcount = refcount - 1 WHERE store_key = ?1",
+                    params![previous_key],
+                )
+                .map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to decrement store ref: {e}"),
+                })?;
+        }
+
         // Increment store ref
         self.tx
             .execute(
@@ -193,6 +429,19 @@ impl<'a> InstallTransaction<'a> {
         Ok(())
     }
 
+    /// Record the tap a keg's formula came from, or `None` for homebrew/core
+    pub fn record_tap(&self, name: &str, tap: Option<&str>) -> Result<(), Error> {
+        self.tx
+            .execute(
+                "UPDATE installed_kegs SET tap = ?2 WHERE name = ?1",
+                params![name, tap],
+            )
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to record tap: {e}"),
+            })?;
+        Ok(())
+    }
+
     pub fn record_linked_file(
         &self,
         name: &str,
@@ -213,6 +462,53 @@ impl<'a> InstallTransaction<'a> {
         Ok(())
     }
 
+    /// Replace the recorded direct dependencies of a keg
+    pub fn record_dependencies(&self, name: &str, dependencies: &[String]) -> Result<(), Error> {
+        self.tx
+            .execute(
+                "DELETE FROM keg_dependencies WHERE name = ?1",
+                params![name],
+            )
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to remove dependency records: {e}"),
+            })?;
+
+        for dependency in dependencies {
+            self.tx
+                .execute(
+                    "INSERT OR IGNORE INTO keg_dependencies (name, dependency) VALUES (?1, ?2)",
+                    params![name, dependency],
+                )
+                .map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to record dependency: {e}"),
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the aliases and old names a keg can also be looked up by
+    pub fn record_aliases(&self, name: &str, aliases: &[String]) -> Result<(), Error> {
+        self.tx
+            .execute("DELETE FROM keg_aliases WHERE name = ?1", params![name])
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to remove alias records: {e}"),
+            })?;
+
+        for alias in aliases {
+            self.tx
+                .execute(
+                    "INSERT OR REPLACE INTO keg_aliases (alias, name) VALUES (?1, ?2)",
+                    params![alias, name],
+                )
+                .map_err(|e| Error::StoreCorruption {
+                    message: format!("failed to record alias: {e}"),
+                })?;
+        }
+
+        Ok(())
+    }
+
     pub fn record_uninstall(&self, name: &str) -> Result<Option<String>, Error> {
         // Get the store_key before removing
         let store_key: Option<String> = self
@@ -238,6 +534,22 @@ impl<'a> InstallTransaction<'a> {
                 message: format!("failed to remove keg files records: {e}"),
             })?;
 
+        // Remove the keg's own dependency edges; edges pointing at it stay with their dependents
+        self.tx
+            .execute(
+                "DELETE FROM keg_dependencies WHERE name = ?1",
+                params![name],
+            )
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to remove dependency records: {e}"),
+            })?;
+
+        self.tx
+            .execute("DELETE FROM keg_aliases WHERE name = ?1", params![name])
+            .map_err(|e| Error::StoreCorruption {
+                message: format!("failed to remove alias records: {e}"),
+            })?;
+
         // Decrement store ref if we had one
         if let Some(ref key) = store_key {
             self.tx
@@ -272,7 +584,7 @@ mod tests {
 
         {
             let tx = db.transaction().unwrap();
-            tx.record_install("foo", "1.0.0", "abc123").unwrap();
+            tx.record_install("foo", "1.0.0", "abc123", true).unwrap();
             tx.commit().unwrap();
         }
 
@@ -289,7 +601,7 @@ mod tests {
 
         {
             let tx = db.transaction().unwrap();
-            tx.record_install("foo", "1.0.0", "abc123").unwrap();
+            tx.record_install("foo", "1.0.0", "abc123", true).unwrap();
             // Don't commit - transaction will be rolled back when dropped
         }
 
@@ -306,8 +618,10 @@ mod tests {
 
         {
             let tx = db.transaction().unwrap();
-            tx.record_install("foo", "1.0.0", "shared123").unwrap();
-            tx.record_install("bar", "2.0.0", "shared123").unwrap();
+            tx.record_install("foo", "1.0.0", "shared123", true)
+                .unwrap();
+            tx.record_install("bar", "2.0.0", "shared123", true)
+                .unwrap();
             tx.commit().unwrap();
         }
 
@@ -324,14 +638,209 @@ mod tests {
         assert!(db.get_installed("bar").is_some());
     }
 
+    #[test]
+    fn dependents_only_include_installed_kegs() {
+        let mut db = Database::in_memory().unwrap();
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_install("openssl", "3.0.0", "key1", false)
+                .unwrap();
+            tx.record_install("curl", "8.0.0", "key2", true).unwrap();
+            tx.record_dependencies("curl", &["openssl".to_string()])
+                .unwrap();
+            tx.record_install("wget", "1.0.0", "key3", true).unwrap();
+            tx.record_dependencies("wget", &["openssl".to_string()])
+                .unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(db.get_dependencies("curl").unwrap(), vec!["openssl"]);
+        assert_eq!(db.get_dependents("openssl").unwrap(), vec!["curl", "wget"]);
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_uninstall("wget").unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(db.get_dependents("openssl").unwrap(), vec!["curl"]);
+        assert!(db.get_dependencies("wget").unwrap().is_empty());
+    }
+
+    #[test]
+    fn resolves_installed_kegs_by_alias() {
+        let mut db = Database::in_memory().unwrap();
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_install("python@3.13", "3.13.1", "key1", true)
+                .unwrap();
+            tx.record_aliases(
+                "python@3.13",
+                &["python".to_string(), "python3".to_string()],
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(
+            db.resolve_installed_name("python").as_deref(),
+            Some("python@3.13")
+        );
+        assert_eq!(
+            db.resolve_installed_name("python@3.13").as_deref(),
+            Some("python@3.13")
+        );
+        assert!(db.resolve_installed_name("ruby").is_none());
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_uninstall("python@3.13").unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert!(db.resolve_installed_name("python").is_none());
+    }
+
+    #[test]
+    fn registers_and_removes_taps() {
+        let db = Database::in_memory().unwrap();
+
+        db.add_tap("acme/tools", "/srv/taps/old").unwrap();
+        db.add_tap("acme/tools", "https://taps.acme.dev/tools")
+            .unwrap();
+        db.add_tap("corp/infra", "/srv/taps/infra").unwrap();
+
+        let taps = db.list_taps().unwrap();
+        assert_eq!(taps.len(), 2);
+        assert_eq!(taps["acme/tools"], "https://taps.acme.dev/tools");
+
+        assert!(db.remove_tap("corp/infra").unwrap());
+        assert!(!db.remove_tap("corp/infra").unwrap());
+        assert_eq!(db.list_taps().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn requested_flag_survives_reinstall_as_dependency() {
+        let mut db = Database::in_memory().unwrap();
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_install("foo", "1.0.0", "key1", true).unwrap();
+            tx.record_install("libfoo", "1.0.0", "key2", false).unwrap();
+            tx.commit().unwrap();
+        }
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_install("foo", "1.0.0", "key1", false).unwrap();
+            tx.record_install("libfoo", "1.0.0", "key2", true).unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert!(db.get_installed("foo").unwrap().requested);
+        assert!(db.get_installed("libfoo").unwrap().requested);
+    }
+
+    #[test]
+    fn migrates_legacy_schema_treating_kegs_as_requested() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("zb.sqlite3");
+
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE installed_kegs (
+                    name TEXT PRIMARY KEY,
+                    version TEXT NOT NULL,
+                    store_key TEXT NOT NULL,
+                    installed_at INTEGER NOT NULL
+                );
+                INSERT INTO installed_kegs VALUES ('legacy', '1.0.0', 'key', 0);",
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&path).unwrap();
+        let legacy = db.get_installed("legacy").unwrap();
+        assert!(legacy.requested);
+        assert_eq!(legacy.tap, None);
+    }
+
+    #[test]
+    fn records_the_tap_a_keg_came_from() {
+        let mut db = Database::in_memory().unwrap();
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_install("deployctl", "2.0.0", "key1", true)
+                .unwrap();
+            tx.record_tap("deployctl", Some("acme/tools")).unwrap();
+            tx.record_install("jq", "1.7.1", "key2", true).unwrap();
+            tx.record_tap("jq", None).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let keg = db.get_installed("deployctl").unwrap();
+        assert_eq!(keg.formula_name(), "acme/tools/deployctl");
+        assert_eq!(db.get_installed("jq").unwrap().formula_name(), "jq");
+        assert_eq!(
+            db.resolve_installed_name("acme/tools/deployctl").as_deref(),
+            Some("deployctl")
+        );
+        assert!(db.resolve_installed_name("other/tap/deployctl").is_none());
+        assert!(db.resolve_installed_name("acme/tools/jq").is_none());
+    }
+
+    #[test]
+    fn reinstall_keeps_single_store_ref() {
+        let mut db = Database::in_memory().unwrap();
+
+        for _ in 0..2 {
+            let tx = db.transaction().unwrap();
+            tx.record_install("foo", "1.0.0", "key1", true).unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(db.get_store_refcount("key1"), 1);
+    }
+
+    #[test]
+    fn upgrade_moves_store_ref_to_new_key() {
+        let mut db = Database::in_memory().unwrap();
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_install("foo", "1.0.0", "old_key", true).unwrap();
+            tx.commit().unwrap();
+        }
+
+        {
+            let tx = db.transaction().unwrap();
+            tx.record_install("foo", "2.0.0", "new_key", true).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let installed = db.get_installed("foo").unwrap();
+        assert_eq!(installed.version, "2.0.0");
+        assert_eq!(installed.store_key, "new_key");
+        assert_eq!(db.get_store_refcount("old_key"), 0);
+        assert_eq!(db.get_store_refcount("new_key"), 1);
+        assert_eq!(
+            db.get_unreferenced_store_keys().unwrap(),
+            vec!["old_key".to_string()]
+        );
+    }
+
     #[test]
     fn get_unreferenced_store_keys() {
         let mut db = Database::in_memory().unwrap();
 
         {
             let tx = db.transaction().unwrap();
-            tx.record_install("foo", "1.0.0", "key1").unwrap();
-            tx.record_install("bar", "2.0.0", "key2").unwrap();
+            tx.record_install("foo", "1.0.0", "key1", true).unwrap();
+            tx.record_install("bar", "2.0.0", "key2", true).unwrap();
             tx.commit().unwrap();
         }
 
@@ -355,7 +864,7 @@ mod tests {
 
         {
             let tx = db.transaction().unwrap();
-            tx.record_install("foo", "1.0.0", "abc123").unwrap();
+            tx.record_install("foo", "1.0.0", "abc123", true).unwrap();
             tx.record_linked_file(
                 "foo",
                 "1.0.0",
@@ -1,7 +1,9 @@
+pub mod archive;
 pub mod blob;
 pub mod db;
 pub mod store;
 
+pub use archive::{ArchivedBottle, CacheArchive, CacheManifest};
 pub use blob::{BlobCache, BlobWriter};
 pub use db::{Database, InstallTransaction, InstalledKeg};
 pub use store::Store;